@@ -0,0 +1,258 @@
+use crate::chess::layout::fair_seed;
+use crate::chess::{Chessboard, Rules, Status, Turn};
+use crate::engine::{Control, Engine, Limits};
+
+/// Games still running after this many plies are drawn.
+pub const MAX_PLIES: usize = 400;
+/// Standard normal quantile of a two-sided 95% confidence interval.
+const Z95: f64 = 1.96;
+/// Games of each result the SPRT assumes before the first, so that the spread
+/// is never zero.
+const PRIOR: f64 = 0.5;
+
+/// Games between two engines on random fair layouts; each layout is played twice
+/// so that both engines get both colours.
+pub struct Arena {
+    engines: [Box<dyn Engine>; 2],
+    rules: Rules,
+    limits: Limits,
+    /// Layout of the pair of games being played.
+    seed: u64,
+    stats: Stats,
+}
+
+/// How a game ended for the first engine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Score {
+    Win,
+    Loss,
+    Draw,
+}
+
+/// Results so far, from the first engine's point of view.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Stats {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    plies: u64,
+}
+
+/// One finished game.
+pub struct Game {
+    pub seed: u64,
+    /// The side the first engine played.
+    pub first: Turn,
+    pub score: Score,
+    pub plies: usize,
+}
+
+impl Arena {
+    pub fn new(engines: [Box<dyn Engine>; 2], rules: Rules, limits: Limits) -> Arena {
+        Arena { engines, rules, limits, seed: 0, stats: Stats::default() }
+    }
+
+    pub fn names(&self) -> [&'static str; 2] {
+        [self.engines[0].name(), self.engines[1].name()]
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Plays the next game: a new layout with the first engine as A, then the
+    /// same layout with the colours swapped.
+    pub fn play(&mut self) -> Game {
+        let first = if self.stats.games().is_multiple_of(2) {
+            self.seed = fair_seed(self.rules);
+            Turn::A
+        } else {
+            Turn::B
+        };
+        let mut board = Chessboard::from_seed(self.rules, self.seed);
+        let mut plies = 0;
+        let winner = loop {
+            match board.check() {
+                Status::Win(who) => break Some(who),
+                Status::Draw => break None,
+                Status::Free(_) if plies >= MAX_PLIES => break None,
+                Status::Free(turn) => {
+                    let engine = &mut self.engines[if turn == first { 0 } else { 1 }];
+                    let (r, c, op) = engine.search(&board, self.limits, &Control::default()).mov;
+                    board = board.next(r, c, op).unwrap();
+                    plies += 1;
+                }
+            }
+        };
+        let score = match winner {
+            Some(who) if who == first => Score::Win,
+            Some(_) => Score::Loss,
+            None => Score::Draw,
+        };
+        self.stats.add(score, plies);
+        Game { seed: self.seed, first, score, plies }
+    }
+}
+
+impl Stats {
+    fn add(&mut self, score: Score, plies: usize) {
+        match score {
+            Score::Win => self.wins += 1,
+            Score::Loss => self.losses += 1,
+            Score::Draw => self.draws += 1,
+        }
+        self.plies += plies as u64;
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    pub fn average_plies(&self) -> f64 {
+        self.plies as f64 / self.games().max(1) as f64
+    }
+
+    /// Points per game, a draw counting half.
+    pub fn score(&self) -> f64 {
+        self.moments(0.0).0
+    }
+
+    /// Spread of the points of a single game around `score`.
+    fn variance(&self) -> f64 {
+        self.moments(0.0).1
+    }
+
+    /// Points per game and their spread, with `prior` games of each result added.
+    fn moments(&self, prior: f64) -> (f64, f64) {
+        let (wins, losses, draws) = (self.wins as f64 + prior, self.losses as f64 + prior, self.draws as f64 + prior);
+        let games = (wins + losses + draws).max(1.0);
+        let score = (wins + draws / 2.0) / games;
+        (score, (wins * (1.0 - score).powi(2) + losses * score.powi(2) + draws * (0.5 - score).powi(2)) / games)
+    }
+
+    /// The Elo difference the score stands for, with a 95% confidence interval
+    /// from the spread of the game results; infinite at a score of 0 or 1.
+    pub fn elo(&self) -> (f64, f64, f64) {
+        let score = self.score();
+        let margin = Z95 * (self.variance() / self.games().max(1) as f64).sqrt();
+        (elo(score), elo(score - margin), elo(score + margin))
+    }
+}
+
+/// Elo difference at which the first side expects `score` points per game.
+fn elo(score: f64) -> f64 {
+    -400.0 * (1.0 / score.clamp(0.0, 1.0) - 1.0).log10()
+}
+
+/// Points per game the first side expects at an Elo difference of `elo`.
+fn expected(elo: f64) -> f64 {
+    1.0 / (1.0 + 10_f64.powf(-elo / 400.0))
+}
+
+/// A sequential probability ratio test of whether the first engine is `elo1`
+/// rather than `elo0` Elo stronger, wrongly accepting `elo1` at most `alpha` of
+/// the time and wrongly accepting `elo0` at most `beta` of the time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+/// What the test decided.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// `elo1` holds: the first engine is the better one.
+    Better,
+    /// `elo0` holds: the first engine is no better.
+    Worse,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Sprt { elo0: 0.0, elo1: 5.0, alpha: 0.05, beta: 0.05 }
+    }
+}
+
+impl Sprt {
+    /// Log-likelihood ratio of the results, taking the points per game to be
+    /// normally distributed with the spread seen so far, after `PRIOR` games of
+    /// each result.
+    pub fn llr(&self, stats: &Stats) -> f64 {
+        let (score, variance) = stats.moments(PRIOR);
+        let (s0, s1) = (expected(self.elo0), expected(self.elo1));
+        stats.games() as f64 * (s1 - s0) * (2.0 * score - s0 - s1) / (2.0 * variance)
+    }
+
+    /// The log-likelihood ratios at which `elo0` and `elo1` are accepted.
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    pub fn verdict(&self, stats: &Stats) -> Option<Verdict> {
+        let llr = self.llr(stats);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            Some(Verdict::Better)
+        } else if llr <= lower {
+            Some(Verdict::Worse)
+        } else {
+            None
+        }
+    }
+
+    /// A plain-text summary of the test for `names`, the first engine first.
+    pub fn report(&self, names: [&str; 2], stats: &Stats) -> String {
+        let (elo, low, high) = stats.elo();
+        let (lower, upper) = self.bounds();
+        let result = match self.verdict(stats) {
+            Some(Verdict::Better) => "H1 accepted: the first engine is better",
+            Some(Verdict::Worse) => "H0 accepted: the first engine is no better",
+            None => "inconclusive",
+        };
+        [
+            format!("Engines: {} vs {}", names[0], names[1]),
+            format!("SPRT: elo0 {} elo1 {} alpha {} beta {}", self.elo0, self.elo1, self.alpha, self.beta),
+            format!("Result: {}", result),
+            format!("LLR: {:.3} ({:.3}, {:.3})", self.llr(stats), lower, upper),
+            format!("Games: {} (+{} -{} ={})", stats.games(), stats.wins, stats.losses, stats.draws),
+            format!("Score: {:.1}%, average {:.1} plies", stats.score() * 100.0, stats.average_plies()),
+            format!("Elo: {:+.1} (95% {:+.1} to {:+.1})", elo, low, high),
+        ]
+        .join("\n")
+            + "\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Games until the test decides when every game ends in `score`.
+    fn games_to_verdict(score: Score) -> (u32, Option<Verdict>) {
+        let sprt = Sprt::default();
+        let mut stats = Stats::default();
+        while stats.games() < 1000 {
+            stats.add(score, 1);
+            if let Some(verdict) = sprt.verdict(&stats) {
+                return (stats.games(), Some(verdict));
+            }
+        }
+        (stats.games(), None)
+    }
+
+    #[test]
+    fn a_clean_sweep_accepts_h1_quickly() {
+        let (games, verdict) = games_to_verdict(Score::Win);
+        assert_eq!(verdict, Some(Verdict::Better));
+        assert!(games <= 40, "took {} games", games);
+    }
+
+    #[test]
+    fn a_clean_loss_accepts_h0_quickly() {
+        let (games, verdict) = games_to_verdict(Score::Loss);
+        assert_eq!(verdict, Some(Verdict::Worse));
+        assert!(games <= 40, "took {} games", games);
+    }
+}
@@ -0,0 +1,53 @@
+use myomyw_chess::chess::tablebase::{Tablebase, Value};
+use myomyw_chess::chess::{self, Chessboard, Rules};
+use myomyw_chess::record::notation;
+
+/// Most positions a tablebase may be generated for, about a gigabyte of memory;
+/// every 4x4 game fits, larger boards are refused before exploring.
+const MAX_POSITIONS: usize = 10_000_000;
+/// Positions found between progress lines.
+const PROGRESS: usize = 1_000_000;
+/// Longest optimal line shown for the start; drawn games never end.
+const SOLUTION_PLIES: usize = 40;
+
+/// Solves the game on a board of the given size under the default rules and
+/// writes the solution as a tablebase file for the game to load.
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let (size, file) = match args.as_slice() {
+        [_, size, file] => match size.parse::<usize>() {
+            Ok(size) if (chess::MIN_SIZE..=chess::N).contains(&size) => (size, file),
+            _ => {
+                eprintln!("棋盘边长须为 {}-{}", chess::MIN_SIZE, chess::N);
+                std::process::exit(2);
+            }
+        },
+        _ => {
+            eprintln!("用法：solve <棋盘边长> <残局库文件名>");
+            std::process::exit(2);
+        }
+    };
+    let start = Chessboard::start(Rules { size, ..Rules::default() });
+    println!("生成中，最多 {} 个局面……", MAX_POSITIONS);
+    let progress = |positions| {
+        if positions % PROGRESS == 0 {
+            println!("已找到 {} 个局面", positions);
+        }
+    };
+    let Some(tablebase) = Tablebase::generate(&start, MAX_POSITIONS, progress) else {
+        eprintln!("局面过多，无法求解！");
+        std::process::exit(1);
+    };
+    let value = match tablebase.probe(&start).unwrap() {
+        Value::Win(plies) => format!("{} 必胜，{} 步内结束", start.turn(), plies),
+        Value::Loss(plies) => format!("{} 必败，{} 步内结束", start.turn(), plies),
+        Value::Draw => String::from("和棋"),
+    };
+    println!("共 {} 个局面，开局 {}", tablebase.positions(), value);
+    let line = tablebase.line(&start, SOLUTION_PLIES);
+    println!("最佳变化：{}", line.iter().map(notation).collect::<Vec<_>>().join(", "));
+    if let Err(error) = tablebase.save(file) {
+        eprintln!("保存失败：{}", error);
+        std::process::exit(1);
+    }
+}
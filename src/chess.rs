@@ -3,9 +3,16 @@ use std::fmt::{Display, Formatter};
 
 use crate::chess::rules::{count_cross, count_snake, Tense};
 
+pub mod handicap;
+pub mod layout;
 pub mod rules;
+pub mod symmetry;
+pub mod tablebase;
 
 pub const N: usize = 6;
+/// Smallest board the rules allow; boards from here up to `N` use the top-left
+/// corner of the `N * N` grid.
+pub const MIN_SIZE: usize = 4;
 
 #[derive(Hash, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Chess {
@@ -14,10 +21,57 @@ pub enum Chess {
     Void,
 }
 
-#[derive(Hash, Clone, Debug)]
+/// The move tuple used for passing; its coordinates are ignored.
+pub const PASS: (i32, i32, Operation) = (-1, -1, Operation::Pass);
+
+/// Distinct moves over all positions, see `move_index`.
+pub const MOVES: usize = N * N * 4 + 1;
+
+/// Length of `Rules::to_bytes`.
+pub const RULE_BYTES: usize = 7;
+
+#[derive(Hash, Clone, Debug, PartialEq, Eq)]
 pub struct Chessboard {
     chessboard: [[Chess; N]; N],
     next_turn: Turn,
+    rules: Rules,
+    passes: u8,
+    /// Plies left of a move handicap; in every other one, starting with the
+    /// second, the stronger side sits out its turn by passing.
+    handicap_plies: u8,
+}
+
+/// Optional rules that can be switched on for a game.
+#[derive(Hash, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rules {
+    /// A player may pass instead of moving a stone.
+    pub pass: bool,
+    /// Flipping still runs after a pass.
+    pub flip_after_pass: bool,
+    /// The game ends after two passes in a row, won by the side with fewer stones.
+    pub double_pass_ends: bool,
+    /// Shortest alternating run that counts as a snake.
+    pub min_snake: usize,
+    /// Both ends of a snake must touch the board edge or an empty square.
+    pub bounded_snakes: bool,
+    /// How the tense of a snake is decided.
+    pub tense: TenseRule,
+    /// Rows and columns of the board, from `MIN_SIZE` to `N`.
+    pub size: usize,
+}
+
+#[derive(Hash, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TenseRule {
+    /// The two end stones agree.
+    Ends,
+    /// The two end stones agree and the middle stone is of the same colour,
+    /// so that the tense side holds the middle of the snake as well.
+    ///
+    /// Snakes alternate, so this depends on the length alone: the middle stone
+    /// matches the ends at 5 or 9 stones but never at 3 or 7, and on boards up
+    /// to `N` only 5-stone snakes can flip. Counting stones instead would change
+    /// nothing, as the end colour always has one stone more.
+    EndsAndMiddle,
 }
 
 #[derive(Hash, Clone, Debug, PartialEq, Eq)]
@@ -26,25 +80,41 @@ pub enum Turn {
     B,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Hash, Clone, Debug, PartialEq, Eq)]
 pub enum Operation {
     Up,
     Down,
     Left,
     Right,
+    Pass,
 }
 
 #[derive(Clone, Debug)]
 pub enum Status {
     Win(Turn),
+    Draw,
     Free(Turn),
 }
 
-#[derive(Debug)]
+/// Why a move is illegal; coordinates are 0-based like those of the move.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Errors {
-    OutOfBound,
-    WrongTurn,
-    Stuck,
+    /// The stone to move is off the board.
+    OriginOutOfBound(i32, i32),
+    /// The stone would leave the board.
+    DestinationOutOfBound(i32, i32),
+    /// There is no stone to move.
+    EmptyOrigin(i32, i32),
+    /// The stone belongs to the other side.
+    OpponentStone(i32, i32),
+    /// The target square is taken.
+    Occupied(i32, i32),
+    /// The game has already ended.
+    GameOver,
+    /// Passing is not allowed by the rules.
+    PassForbidden,
+    /// The side to move sits out this turn under a move handicap and must pass.
+    MustPass,
 }
 
 impl Chess {
@@ -58,11 +128,12 @@ impl Chess {
 
 impl Display for Chessboard {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        for (i, row) in self.chessboard.iter().enumerate() {
+        let size = self.rules.size;
+        for (i, row) in self.chessboard[..size].iter().enumerate() {
             if i != 0 {
                 writeln!(f)?;
             }
-            for (j, chess) in row.iter().enumerate() {
+            for (j, chess) in row[..size].iter().enumerate() {
                 if j != 0 {
                     write!(f, " ")?;
                 }
@@ -77,55 +148,195 @@ impl Display for Chessboard {
     }
 }
 
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            pass: false,
+            flip_after_pass: false,
+            double_pass_ends: true,
+            min_snake: 2,
+            bounded_snakes: false,
+            tense: TenseRule::Ends,
+            size: N,
+        }
+    }
+}
+
+impl Rules {
+    /// One byte per rule, in the order of the fields, for files that must
+    /// match the rules they were made under.
+    pub fn to_bytes(self) -> [u8; RULE_BYTES] {
+        [
+            self.pass as u8,
+            self.flip_after_pass as u8,
+            self.double_pass_ends as u8,
+            self.min_snake as u8,
+            self.bounded_snakes as u8,
+            match self.tense {
+                TenseRule::Ends => 0,
+                TenseRule::EndsAndMiddle => 1,
+            },
+            self.size as u8,
+        ]
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Rules> {
+        if bytes.len() != RULE_BYTES {
+            return None;
+        }
+        let flag = |byte: u8| match byte {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        };
+        Some(Rules {
+            pass: flag(bytes[0])?,
+            flip_after_pass: flag(bytes[1])?,
+            double_pass_ends: flag(bytes[2])?,
+            min_snake: bytes[3] as usize,
+            bounded_snakes: flag(bytes[4])?,
+            tense: match bytes[5] {
+                0 => TenseRule::Ends,
+                1 => TenseRule::EndsAndMiddle,
+                _ => return None,
+            },
+            size: Some(bytes[6] as usize).filter(|size| (MIN_SIZE..=N).contains(size))?,
+        })
+    }
+}
+
 impl Chessboard {
-    pub fn new() -> Chessboard {
+    /// The classic start on the board of `rules`: A in the top-left corner and B
+    /// in the bottom-right, each as two arms of half the board.
+    pub fn start(rules: Rules) -> Chessboard {
+        let size = rules.size;
+        let arm = size.div_ceil(2);
         let mut board = [[Chess::Void; N]; N];
         // place chess
-        board[2][0] = Chess::A;
-        board[1][0] = Chess::A;
-        board[0][0] = Chess::A;
-        board[0][1] = Chess::A;
-        board[0][2] = Chess::A;
-        board[N - 3][N - 1] = Chess::B;
-        board[N - 2][N - 1] = Chess::B;
-        board[N - 1][N - 1] = Chess::B;
-        board[N - 1][N - 2] = Chess::B;
-        board[N - 1][N - 3] = Chess::B;
+        for i in 0..arm {
+            board[i][0] = Chess::A;
+            board[0][i] = Chess::A;
+            board[size - 1 - i][size - 1] = Chess::B;
+            board[size - 1][size - 1 - i] = Chess::B;
+        }
         Chessboard {
             chessboard: board,
             next_turn: Turn::A,
+            rules,
+            passes: 0,
+            handicap_plies: 0,
         }
     }
-    pub fn next(&self, r: i32, c: i32, op: Operation) -> Result<Chessboard, Errors> {
-        let dest = match op {
-            Operation::Up => (r - 1, c),
-            Operation::Down => (r + 1, c),
-            Operation::Left => (r, c - 1),
-            Operation::Right => (r, c + 1),
+    /// A position under `rules` with A to move, drawn as rows of `A`, `B` and
+    /// `.`; cells not drawn are empty.
+    #[cfg(test)]
+    pub fn from_rows(rules: Rules, rows: &[&str]) -> Chessboard {
+        let mut board = Chessboard::start(rules);
+        board.chessboard = [[Chess::Void; N]; N];
+        for (r, row) in rows.iter().enumerate() {
+            for (c, cell) in row.chars().enumerate() {
+                board.chessboard[r][c] = match cell {
+                    'A' => Chess::A,
+                    'B' => Chess::B,
+                    _ => Chess::Void,
+                };
+            }
+        }
+        board
+    }
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+    /// The same position with `turn` to move.
+    pub fn with_turn(self, turn: Turn) -> Chessboard {
+        Chessboard { next_turn: turn, ..self }
+    }
+    pub fn at(&self, r: usize, c: usize) -> Chess {
+        self.chessboard[r][c]
+    }
+    /// The side to move, or the side that would move if the game is over.
+    pub fn turn(&self) -> &Turn {
+        &self.next_turn
+    }
+    /// Checks a move without playing it and reports the first reason it is illegal.
+    pub fn validate_move(&self, r: i32, c: i32, op: &Operation) -> Result<(), Errors> {
+        if !matches!(self.check(), Status::Free(_)) {
+            return Err(Errors::GameOver);
+        }
+        let dest = match destination(r, c, op) {
+            _ if self.sits_out() && *op != Operation::Pass => return Err(Errors::MustPass),
+            Some(dest) => dest,
+            None if self.rules.pass || self.sits_out() => return Ok(()),
+            None => return Err(Errors::PassForbidden),
         };
-        if !(0..N as i32).contains(&r) || !(0..N as i32).contains(&c) || !(0..N as i32).contains(&dest.0) || !(0..N as i32).contains(&dest.1) {
-            return Err(Errors::OutOfBound);
+        if !self.on_board(r, c) {
+            return Err(Errors::OriginOutOfBound(r, c));
+        }
+        if !self.on_board(dest.0, dest.1) {
+            return Err(Errors::DestinationOutOfBound(dest.0, dest.1));
+        }
+        match self.chessboard[r as usize][c as usize] {
+            Chess::Void => return Err(Errors::EmptyOrigin(r, c)),
+            chess if !chess.check(&self.next_turn) => return Err(Errors::OpponentStone(r, c)),
+            _ => (),
+        }
+        if self.chessboard[dest.0 as usize][dest.1 as usize] != Chess::Void {
+            return Err(Errors::Occupied(dest.0, dest.1));
+        }
+        Ok(())
+    }
+    pub fn size(&self) -> usize {
+        self.rules.size
+    }
+    pub fn on_board(&self, r: i32, c: i32) -> bool {
+        (0..self.rules.size as i32).contains(&r) && (0..self.rules.size as i32).contains(&c)
+    }
+    pub fn next(&self, r: i32, c: i32, op: Operation) -> Result<Chessboard, Errors> {
+        self.validate_move(r, c, &op)?;
+        let dest = match destination(r, c, &op) {
+            Some(dest) => (dest.0 as usize, dest.1 as usize),
+            None => return Ok(self.pass()),
         };
         let orig = (r as usize, c as usize);
-        let dest = (dest.0 as usize, dest.1 as usize);
-        if !self.chessboard[orig.0][orig.1].check(&self.next_turn) {
-            return Err(Errors::WrongTurn);
-        };
-        if self.chessboard[dest.0][dest.1] != Chess::Void {
-            return Err(Errors::Stuck);
-        };
         let mut board = self.clone();
         board.chessboard[orig.0][orig.1] = Chess::Void;
         board.chessboard[dest.0][dest.1] = match self.next_turn {
             Turn::A => Chess::A,
             Turn::B => Chess::B,
         };
-        board.next_turn = match self.next_turn {
+        board.end_turn();
+        board.passes = 0;
+        Ok(flip(board))
+    }
+    fn pass(&self) -> Chessboard {
+        let mut board = self.clone();
+        board.end_turn();
+        if self.sits_out() {
+            // the weaker side's extra move has already been flipped, and a turn
+            // sat out is no pass towards ending the game
+            return board;
+        }
+        board.passes = self.passes.saturating_add(1);
+        if self.rules.flip_after_pass {
+            flip(board)
+        } else {
+            board
+        }
+    }
+    fn end_turn(&mut self) {
+        self.next_turn = match self.next_turn {
             Turn::A => Turn::B,
             Turn::B => Turn::A,
         };
-        Ok(flip(board))
+        self.handicap_plies = self.handicap_plies.saturating_sub(1);
     }
+    /// Whether the side to move is the stronger side sitting out a turn of a
+    /// move handicap.
+    pub fn sits_out(&self) -> bool {
+        self.handicap_plies % 2 == 1
+    }
+    /// Whether the game is won, drawn or still on. A side to move that has no
+    /// legal move, which needs every stone boxed in and no pass rule, draws.
     pub fn check(&self) -> Status {
         let mut count = (0, 0);
         for row in self.chessboard {
@@ -140,33 +351,60 @@ impl Chessboard {
         match count {
             (0, x) if x > 0 => Status::Win(Turn::A),
             (x, 0) if x > 0 => Status::Win(Turn::B),
+            (a, b) if self.rules.double_pass_ends && self.passes >= 2 => match a.cmp(&b) {
+                std::cmp::Ordering::Less => Status::Win(Turn::A),
+                std::cmp::Ordering::Greater => Status::Win(Turn::B),
+                std::cmp::Ordering::Equal => Status::Draw,
+            },
+            _ if !self.can_move() => Status::Draw,
             _ => Status::Free(self.next_turn.clone()),
         }
     }
+    /// Whether the side to move has a legal move, without listing them.
+    fn can_move(&self) -> bool {
+        self.rules.pass
+            || self.sits_out()
+            || (0..self.rules.size).any(|r| {
+                (0..self.rules.size).any(|c| {
+                    self.chessboard[r][c].check(&self.next_turn)
+                        && [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|(dr, dc)| {
+                            let (r, c) = (r as i32 + dr, c as i32 + dc);
+                            self.on_board(r, c) && self.chessboard[r as usize][c as usize] == Chess::Void
+                        })
+                })
+            })
+    }
     pub fn available(&self) -> Vec<(i32, i32, Operation)> {
+        if self.sits_out() {
+            return vec![PASS];
+        }
         let mut result = Vec::new();
-        for r in 0..N {
-            for c in 0..N {
+        let size = self.rules.size;
+        for r in 0..size {
+            for c in 0..size {
                 if self.chessboard[r][c] != match self.next_turn {
                     Turn::A => Chess::A,
                     Turn::B => Chess::B,
                 } {
                     continue;
                 }
-                if (1..N).contains(&r) && self.chessboard[r - 1][c] == Chess::Void {
+                if (1..size).contains(&r) && self.chessboard[r - 1][c] == Chess::Void {
                     result.push((r as i32, c as i32, Operation::Up));
                 }
-                if (0..N - 1).contains(&r) && self.chessboard[r + 1][c] == Chess::Void {
+                if (0..size - 1).contains(&r) && self.chessboard[r + 1][c] == Chess::Void {
                     result.push((r as i32, c as i32, Operation::Down));
                 }
-                if (1..N).contains(&c) && self.chessboard[r][c - 1] == Chess::Void {
+                if (1..size).contains(&c) && self.chessboard[r][c - 1] == Chess::Void {
                     result.push((r as i32, c as i32, Operation::Left));
                 }
-                if (0..N - 1).contains(&c) && self.chessboard[r][c + 1] == Chess::Void {
+                if (0..size - 1).contains(&c) && self.chessboard[r][c + 1] == Chess::Void {
                     result.push((r as i32, c as i32, Operation::Right));
                 }
             }
         }
+        if self.rules.pass {
+            result.push(PASS);
+        }
         result
     }
     pub fn state(&self) -> (i32, i32) {
@@ -194,6 +432,48 @@ impl Display for Turn {
     }
 }
 
+/// Shows coordinates 1-based, as the CLI and notation do.
+impl Display for Errors {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Errors::OriginOutOfBound(r, c) => write!(f, "origin ({}, {}) is off the board", r + 1, c + 1),
+            Errors::DestinationOutOfBound(r, c) => write!(f, "destination ({}, {}) is off the board", r + 1, c + 1),
+            Errors::EmptyOrigin(r, c) => write!(f, "no stone at ({}, {})", r + 1, c + 1),
+            Errors::OpponentStone(r, c) => write!(f, "stone at ({}, {}) belongs to the opponent", r + 1, c + 1),
+            Errors::Occupied(r, c) => write!(f, "destination ({}, {}) is occupied", r + 1, c + 1),
+            Errors::GameOver => write!(f, "the game is already over"),
+            Errors::PassForbidden => write!(f, "passing is not allowed"),
+            Errors::MustPass => write!(f, "this turn is sat out under the handicap and must be passed"),
+        }
+    }
+}
+
+impl std::error::Error for Errors {}
+
+/// A dense index of `mov` below `MOVES`: the four directions of each cell in
+/// row order, then the pass.
+pub fn move_index(mov: &(i32, i32, Operation)) -> usize {
+    let op = match mov.2 {
+        Operation::Up => 0,
+        Operation::Down => 1,
+        Operation::Left => 2,
+        Operation::Right => 3,
+        Operation::Pass => return MOVES - 1,
+    };
+    (mov.0 as usize * N + mov.1 as usize) * 4 + op
+}
+
+/// Where the stone moved by `op` lands, or `None` for a pass.
+fn destination(r: i32, c: i32, op: &Operation) -> Option<(i32, i32)> {
+    match op {
+        Operation::Up => Some((r - 1, c)),
+        Operation::Down => Some((r + 1, c)),
+        Operation::Left => Some((r, c - 1)),
+        Operation::Right => Some((r, c + 1)),
+        Operation::Pass => None,
+    }
+}
+
 fn flip(mut board: Chessboard) -> Chessboard {
     // count snakes
     let snakes = count_snake(&board);
@@ -214,4 +494,19 @@ fn flip(mut board: Chessboard) -> Chessboard {
         }
     }
     board
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_side_with_no_legal_move_draws() {
+        let board = Chessboard::from_rows(Rules::default(), &["AB", "B.", "..B"]);
+        assert!(board.available().is_empty());
+        assert!(matches!(board.check(), Status::Draw));
+        assert_eq!(board.validate_move(0, 0, &Operation::Down), Err(Errors::GameOver));
+        // with passing allowed there is always a move
+        let rules = Rules { pass: true, ..Rules::default() };
+        assert!(matches!(Chessboard::from_rows(rules, &["AB", "B.", "..B"]).check(), Status::Free(Turn::A)));
+    }
+}
@@ -0,0 +1,100 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::chess::{Chess, Chessboard, Turn};
+
+pub const PRESETS: [Odds; 5] = [Odds::Stones(1), Odds::Stones(2), Odds::Stones(3), Odds::Moves(1), Odds::Moves(2)];
+
+#[derive(Hash, Clone, Debug, PartialEq, Eq)]
+pub struct Handicap {
+    pub stronger: Turn,
+    pub odds: Odds,
+}
+
+#[derive(Hash, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Odds {
+    /// The weaker side starts without this many stones, and so has fewer to be
+    /// rid of.
+    Stones(u8),
+    /// The weaker side moves first and makes this many extra moves, while the
+    /// stronger side sits out as many of its turns.
+    Moves(u8),
+}
+
+impl Chessboard {
+    /// Applies the handicap to a starting layout, such as the one from `Chessboard::start`.
+    ///
+    /// Stones are taken farthest from the weaker side's home corner first, which
+    /// removes the tips of the classic corner, and the last stone is always kept.
+    pub fn with_handicap(mut self, handicap: &Handicap) -> Chessboard {
+        let n = match handicap.odds {
+            Odds::Stones(n) => n,
+            Odds::Moves(n) => {
+                self.next_turn = match handicap.stronger {
+                    Turn::A => Turn::B,
+                    Turn::B => Turn::A,
+                };
+                // a move of the weaker side, then a turn sat out, for each extra move
+                self.handicap_plies = n.saturating_mul(2);
+                return self;
+            }
+        };
+        // coordinates as seen from the weaker side's home corner
+        let size = self.size();
+        let home = |(r, c): (usize, usize)| match handicap.stronger {
+            Turn::A => (size - 1 - r, size - 1 - c),
+            Turn::B => (r, c),
+        };
+        let weaker = match handicap.stronger {
+            Turn::A => Chess::B,
+            Turn::B => Chess::A,
+        };
+        let mut stones: Vec<_> = (0..size)
+            .flat_map(|r| (0..size).map(move |c| (r, c)))
+            .filter(|&loc| {
+                let (r, c) = home(loc);
+                self.chessboard[r][c] == weaker
+            })
+            .collect();
+        stones.sort_by_key(|&(r, c)| std::cmp::Reverse(r + c));
+        for &loc in stones.iter().take((n as usize).min(stones.len().saturating_sub(1))) {
+            let (r, c) = home(loc);
+            self.chessboard[r][c] = Chess::Void;
+        }
+        self
+    }
+}
+
+/// `A stones 2` or `A moves 1`: the stronger side, then the odds it gives.
+impl Display for Handicap {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.odds {
+            Odds::Stones(n) => write!(f, "{} stones {}", self.stronger, n),
+            Odds::Moves(n) => write!(f, "{} moves {}", self.stronger, n),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::{Errors, Operation, Rules, PASS};
+
+    #[test]
+    fn move_odds_let_the_weaker_side_move_twice() {
+        let handicap = Handicap { stronger: Turn::A, odds: Odds::Moves(1) };
+        let board = Chessboard::start(Rules::default()).with_handicap(&handicap);
+        assert_eq!(*board.turn(), Turn::B);
+        let (r, c, op) = board.available().swap_remove(0);
+        let board = board.next(r, c, op).unwrap();
+        // A sits out its first turn, which is no pass towards ending the game
+        assert_eq!(board.available(), vec![PASS]);
+        assert_eq!(board.validate_move(0, 0, &Operation::Down), Err(Errors::MustPass));
+        let board = board.next(PASS.0, PASS.1, Operation::Pass).unwrap();
+        assert_eq!((board.turn(), board.passes), (&Turn::B, 0));
+        let (r, c, op) = board.available().swap_remove(0);
+        let board = board.next(r, c, op).unwrap();
+        assert_eq!(*board.turn(), Turn::A);
+        assert!(!board.available().contains(&PASS));
+    }
+}
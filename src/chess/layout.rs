@@ -0,0 +1,109 @@
+use crate::chess::{Chess, Chessboard, Rules, Status, Turn, N};
+use crate::engine::evaluate;
+use crate::engine::features::Weights;
+
+/// Games played per layout by the fairness check.
+const SAMPLES: usize = 100;
+/// Games still running after this many plies count as draws.
+const MAX_PLIES: usize = 200;
+/// Largest accepted distance of A's share of the decided games from one half.
+const MAX_GAP: f64 = 0.15;
+
+impl Chessboard {
+    /// A random layout for `seed` on the board of `rules`: as many stones as
+    /// the classic start, A in the top half, B its 180° rotation.
+    pub fn from_seed(rules: Rules, seed: u64) -> Chessboard {
+        let size = rules.size;
+        let mut rng = SplitMix(seed);
+        let mut cells: Vec<(usize, usize)> = (0..size / 2).flat_map(|r| (0..size).map(move |c| (r, c))).collect();
+        for i in (1..cells.len()).rev() {
+            cells.swap(i, rng.below(i + 1));
+        }
+        let mut board = Chessboard::start(rules);
+        let stones = board.state().0 as usize;
+        board.chessboard = [[Chess::Void; N]; N];
+        for &(r, c) in cells.iter().take(stones) {
+            board.chessboard[r][c] = Chess::A;
+            board.chessboard[size - 1 - r][size - 1 - c] = Chess::B;
+        }
+        board
+    }
+}
+
+/// Draws seeds until the layout passes the fairness check under `rules`.
+pub fn fair_seed(rules: Rules) -> u64 {
+    loop {
+        let seed = rand::random();
+        if fair(&Chessboard::from_seed(rules, seed), seed) {
+            return seed;
+        }
+    }
+}
+
+/// Plays greedy games from the layout and compares how often each side wins.
+fn fair(board: &Chessboard, seed: u64) -> bool {
+    let mut rng = SplitMix(seed);
+    let mut wins = (0, 0);
+    let weights = Weights::default();
+    for _ in 0..SAMPLES {
+        let mut board = board.clone();
+        for _ in 0..MAX_PLIES {
+            if !matches!(board.check(), Status::Free(_)) {
+                break;
+            }
+            // the move with the best static score, as a greedy rollout plays,
+            // ties broken at random
+            let side = board.turn().clone();
+            let mut moves: Vec<_> = board
+                .available()
+                .into_iter()
+                .map(|(r, c, op)| {
+                    let next = board.next(r, c, op).unwrap();
+                    (evaluate(&next, &side, &weights), next)
+                })
+                .collect();
+            let best = moves.iter().map(|(score, _)| *score).max().unwrap();
+            moves.retain(|(score, _)| *score == best);
+            board = moves.swap_remove(rng.below(moves.len())).1;
+        }
+        match board.check() {
+            Status::Win(Turn::A) => wins.0 += 1,
+            Status::Win(Turn::B) => wins.1 += 1,
+            _ => (),
+        }
+    }
+    let decided = wins.0 + wins.1;
+    decided == 0 || (wins.0 as f64 / decided as f64 - 0.5).abs() <= MAX_GAP
+}
+
+/// SplitMix64, kept here so that a seed names the same layout whatever the
+/// `rand` version.
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number below `n`; the bias is negligible for the small `n` used here.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_a_lopsided_layout() {
+        let rules = Rules { size: 4, ..Rules::default() };
+        // greedy games from this layout end 57 to 13 for A
+        assert!(!fair(&Chessboard::from_seed(rules, 2), 2));
+        assert!(fair(&Chessboard::from_seed(rules, 3), 3));
+    }
+}
@@ -1,6 +1,6 @@
 use reunion::{UnionFind, UnionFindTrait};
 
-use crate::chess::{Chess, Chessboard, N};
+use crate::chess::{Chess, Chessboard, TenseRule, N};
 
 #[derive(Hash, Clone, Debug, PartialEq, Eq)]
 pub struct Snake {
@@ -33,6 +33,16 @@ impl Snake {
         &self.tense
     }
 
+    /// (r, c) of every stone in the snake.
+    pub fn cells(&self) -> Vec<(usize, usize)> {
+        (self.slice.0..self.slice.1)
+            .map(|i| match self.axis {
+                Axis::Row => (self.index, i),
+                Axis::Column => (i, self.index),
+            })
+            .collect()
+    }
+
     pub fn flip(&self, board: &mut Chessboard) {
         for i in self.slice.0..self.slice.1 {
             let chess = match self.axis {
@@ -65,7 +75,7 @@ pub fn count_snake(board: &Chessboard) -> Vec<Snake> {
             if board.chessboard[r][c] != Chess::Void {
                 begin = (board.chessboard[r][c], c);
                 while c < N && board.chessboard[r][c] != Chess::Void {
-                    if ((c - begin.1) % 2 == 0) ^ (board.chessboard[r][c] == begin.0) {
+                    if (c - begin.1).is_multiple_of(2) ^ (board.chessboard[r][c] == begin.0) {
                         snake_rows.push((r, begin.0, board.chessboard[r][c - 1], begin.1, c - 1));
                         begin = (board.chessboard[r][c], c);
                     }
@@ -86,7 +96,7 @@ pub fn count_snake(board: &Chessboard) -> Vec<Snake> {
             if board.chessboard[r][c] != Chess::Void {
                 begin = (board.chessboard[r][c], r);
                 while r < N && board.chessboard[r][c] != Chess::Void {
-                    if ((r - begin.1) % 2 == 0) ^ (board.chessboard[r][c] == begin.0) {
+                    if (r - begin.1).is_multiple_of(2) ^ (board.chessboard[r][c] == begin.0) {
                         snake_columns.push((c, begin.0, board.chessboard[r - 1][c], begin.1, r - 1));
                         begin = (board.chessboard[r][c], r);
                     }
@@ -99,18 +109,28 @@ pub fn count_snake(board: &Chessboard) -> Vec<Snake> {
         }
     }
     // collect result
-    snakes.append(&mut collect_snakes(snake_rows, &Axis::Row));
-    snakes.append(&mut collect_snakes(snake_columns, &Axis::Column));
+    snakes.append(&mut collect_snakes(board, snake_rows, &Axis::Row));
+    snakes.append(&mut collect_snakes(board, snake_columns, &Axis::Column));
     snakes
 }
 
-fn collect_snakes(tuples: Vec<(usize, Chess, Chess, usize, usize)>, axis: &Axis) -> Vec<Snake> {
+fn collect_snakes(board: &Chessboard, tuples: Vec<(usize, Chess, Chess, usize, usize)>, axis: &Axis) -> Vec<Snake> {
+    let rules = board.rules();
+    let at = |index: usize, i: usize| match axis {
+        Axis::Row => board.chessboard[index][i],
+        Axis::Column => board.chessboard[i][index],
+    };
     let mut snakes = vec![];
     for (column, b_chess, e_chess, begin, end) in tuples {
-        if begin >= end {
+        if begin >= end || end - begin + 1 < rules.min_snake {
+            continue;
+        }
+        if rules.bounded_snakes
+            && (begin > 0 && at(column, begin - 1) != Chess::Void || end < N - 1 && at(column, end + 1) != Chess::Void) {
             continue;
         }
         let tense = match (b_chess, e_chess) {
+            _ if rules.tense == TenseRule::EndsAndMiddle && !middle_matches_ends(end - begin + 1) => Tense::None,
             (Chess::A, Chess::A) => Tense::A,
             (Chess::B, Chess::B) => Tense::B,
             _ => Tense::None,
@@ -128,7 +148,13 @@ fn collect_snakes(tuples: Vec<(usize, Chess, Chess, usize, usize)>, axis: &Axis)
     snakes
 }
 
-pub fn count_cross(snakes: &Vec<Snake>) -> Vec<Cross> {
+/// Whether the middle stone of an alternating run of `length` stones has the
+/// colour of its ends, that is whether it lies an even number of stones in.
+fn middle_matches_ends(length: usize) -> bool {
+    length % 2 == 1 && ((length - 1) / 2).is_multiple_of(2)
+}
+
+pub fn count_cross(snakes: &Vec<Snake>) -> Vec<Cross<'_>> {
     let mut uf = UnionFind::<&Snake>::new();
     let mut horizontal = vec![];
     let mut vertical = vec![];
@@ -170,4 +196,32 @@ pub fn count_cross(snakes: &Vec<Snake>) -> Vec<Cross> {
         crosses.push(Cross { elements });
     }
     crosses
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::Rules;
+
+    fn tenses(tense: TenseRule, row: &str) -> Vec<Tense> {
+        let board = Chessboard::from_rows(Rules { tense, ..Rules::default() }, &[row]);
+        count_snake(&board).into_iter().map(|snake| snake.tense().clone()).collect()
+    }
+
+    #[test]
+    fn the_middle_stone_matches_the_ends_by_length() {
+        assert!(!middle_matches_ends(3));
+        assert!(middle_matches_ends(5));
+        assert!(!middle_matches_ends(7));
+        assert!(middle_matches_ends(9));
+        // even runs have ends of both colours
+        assert!(!middle_matches_ends(4));
+    }
+
+    #[test]
+    fn only_snakes_with_a_matching_middle_are_tense() {
+        assert_eq!(tenses(TenseRule::Ends, "ABA"), vec![Tense::A]);
+        assert_eq!(tenses(TenseRule::EndsAndMiddle, "ABA"), vec![Tense::None]);
+        assert_eq!(tenses(TenseRule::Ends, "ABABA"), vec![Tense::A]);
+        assert_eq!(tenses(TenseRule::EndsAndMiddle, "ABABA"), vec![Tense::A]);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::chess::{Chess, Chessboard, Operation, Turn};
+
+/// A reflection or rotation of the board, as the flips and transposition it is
+/// made of, applied in that order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Symmetry {
+    flip_rows: bool,
+    flip_columns: bool,
+    transpose: bool,
+    /// Rows and columns of the board it acts on.
+    size: usize,
+}
+
+impl Symmetry {
+    /// The eight reflections and rotations of a board of `size`.
+    pub fn all(size: usize) -> Vec<Symmetry> {
+        (0..8).map(|i| Symmetry { flip_rows: i & 1 != 0, flip_columns: i & 2 != 0, transpose: i & 4 != 0, size }).collect()
+    }
+
+    fn cell(&self, r: i32, c: i32) -> (i32, i32) {
+        let last = self.size as i32 - 1;
+        let r = if self.flip_rows { last - r } else { r };
+        let c = if self.flip_columns { last - c } else { c };
+        if self.transpose { (c, r) } else { (r, c) }
+    }
+
+    /// `mov` as played on the transformed board.
+    pub fn mov(&self, mov: &(i32, i32, Operation)) -> (i32, i32, Operation) {
+        let (r, c, op) = mov;
+        let (dr, dc) = match op {
+            Operation::Up => (-1, 0),
+            Operation::Down => (1, 0),
+            Operation::Left => (0, -1),
+            Operation::Right => (0, 1),
+            Operation::Pass => return mov.clone(),
+        };
+        let (from_r, from_c) = self.cell(*r, *c);
+        let (to_r, to_c) = self.cell(r + dr, c + dc);
+        let op = match (to_r - from_r, to_c - from_c) {
+            (-1, 0) => Operation::Up,
+            (1, 0) => Operation::Down,
+            (0, -1) => Operation::Left,
+            _ => Operation::Right,
+        };
+        (from_r, from_c, op)
+    }
+
+    pub fn board(&self, board: &Chessboard) -> Chessboard {
+        let mut transformed = board.clone();
+        for r in 0..self.size {
+            for c in 0..self.size {
+                let (tr, tc) = self.cell(r as i32, c as i32);
+                transformed.chessboard[tr as usize][tc as usize] = board.chessboard[r][c];
+            }
+        }
+        transformed
+    }
+}
+
+impl Chessboard {
+    /// A text key shared by the position and its symmetric copies, with the
+    /// symmetry taking the board to the copy it names.
+    pub fn canonical(&self) -> (String, Symmetry) {
+        Symmetry::all(self.size())
+            .into_iter()
+            .map(|symmetry| (symmetry.board(self).key(), symmetry))
+            .min_by(|a, b| a.0.cmp(&b.0))
+            .unwrap()
+    }
+
+    /// The cells row by row, then the side to move, the passes so far and the
+    /// plies left of a move handicap.
+    fn key(&self) -> String {
+        let mut key: String = self
+            .chessboard
+            .iter()
+            .flatten()
+            .map(|chess| match chess {
+                Chess::A => 'A',
+                Chess::B => 'B',
+                Chess::Void => '.',
+            })
+            .collect();
+        key.push_str(match self.next_turn {
+            Turn::A => "/A/",
+            Turn::B => "/B/",
+        });
+        key.push_str(&format!("{}/{}", self.passes, self.handicap_plies));
+        key
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::chess::symmetry::Symmetry;
+use crate::chess::{Chess, Chessboard, Operation, Rules, Status, Turn, N, RULE_BYTES};
+
+const MAGIC: &[u8; 5] = b"MYTB3";
+/// Bytes of a key and of an entry in a file.
+const RECORD: usize = 16 + 2;
+
+/// Outcomes for every position reachable from one start under one set of rules,
+/// symmetric positions sharing their entry.
+///
+/// Flips only recolour stones, so every position of a game has the stone count
+/// of its start and tables by stone count, as endgames are solved in other
+/// games, cannot work: the one table would be the whole game. A tablebase is
+/// therefore the solved game, which fits for 4x4 boards and not for larger ones.
+pub struct Tablebase {
+    rules: Rules,
+    /// Canonical keys in ascending order, see `key`.
+    keys: Vec<u128>,
+    /// The entry of each key, see `encode`.
+    entries: Vec<u16>,
+}
+
+/// A solved position for the side to move, with the plies to the end of the game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Value {
+    Win(u32),
+    Loss(u32),
+    /// Drawn, or neither side can force the game to end.
+    Draw,
+}
+
+impl Tablebase {
+    /// Solves every position reachable from `start` by working back from the ends
+    /// of the game; `None` once there are more than `max_positions` of them, or
+    /// at once if the stones of `start` can be arranged in more ways than that.
+    /// `progress` hears of every position found.
+    pub fn generate(start: &Chessboard, max_positions: usize, progress: impl FnMut(usize)) -> Option<Tablebase> {
+        if arrangements(start) > max_positions as u128 {
+            return None;
+        }
+        let (keys, successors) = explore(start, max_positions, progress)?;
+        let entries = solve(&start.rules, &keys, successors);
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+        order.sort_unstable_by_key(|&i| keys[i]);
+        Some(Tablebase {
+            rules: start.rules,
+            keys: order.iter().map(|&i| keys[i]).collect(),
+            entries: order.iter().map(|&i| entries[i]).collect(),
+        })
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn positions(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// The value of `board`, if it is played by the same rules and was reachable
+    /// from the start the tablebase was made for.
+    pub fn probe(&self, board: &Chessboard) -> Option<Value> {
+        if board.rules != self.rules {
+            return None;
+        }
+        let index = self.keys.binary_search(&key(board)).ok()?;
+        Some(decode(self.entries[index]))
+    }
+
+    /// The move keeping the best value: the quickest win, else a draw, else the
+    /// slowest loss.
+    pub fn best(&self, board: &Chessboard) -> Option<((i32, i32, Operation), Value)> {
+        self.probe(board)?;
+        let rank = |value: &Value| match *value {
+            Value::Win(plies) => i64::MAX - plies as i64,
+            Value::Draw => 0,
+            Value::Loss(plies) => i64::MIN + plies as i64,
+        };
+        board
+            .available()
+            .into_iter()
+            .filter_map(|(r, c, op)| {
+                let reply = self.probe(&board.next(r, c, op.clone()).unwrap())?;
+                let value = match reply {
+                    Value::Win(plies) => Value::Loss(plies + 1),
+                    Value::Loss(plies) => Value::Win(plies + 1),
+                    Value::Draw => Value::Draw,
+                };
+                Some(((r, c, op), value))
+            })
+            .max_by_key(|(_, value)| rank(value))
+    }
+
+    /// The moves both sides play by `best` from `board`, at most `plies` of
+    /// them, since a drawn game can go on forever.
+    pub fn line(&self, board: &Chessboard, plies: usize) -> Vec<(i32, i32, Operation)> {
+        let mut board = board.clone();
+        let mut line = vec![];
+        while line.len() < plies && matches!(board.check(), Status::Free(_)) {
+            let Some(((r, c, op), _)) = self.best(&board) else {
+                break;
+            };
+            board = board.next(r, c, op.clone()).unwrap();
+            line.push((r, c, op));
+        }
+        line
+    }
+
+    /// A header naming the rules and the number of positions, then every key as
+    /// a little-endian u128 and every entry as a little-endian u16.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(self.rules.to_bytes());
+        bytes.extend((self.keys.len() as u64).to_le_bytes());
+        bytes.extend(self.keys.iter().flat_map(|key| key.to_le_bytes()));
+        bytes.extend(self.entries.iter().flat_map(|entry| entry.to_le_bytes()));
+        std::fs::write(path, bytes)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Tablebase> {
+        let bytes = std::fs::read(path)?;
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "not a tablebase file");
+        let header = MAGIC.len() + RULE_BYTES + 8;
+        if bytes.len() < header || !bytes.starts_with(MAGIC) {
+            return Err(invalid());
+        }
+        let rules = Rules::from_bytes(&bytes[MAGIC.len()..MAGIC.len() + RULE_BYTES]).ok_or_else(invalid)?;
+        let count = u64::from_le_bytes(bytes[header - 8..header].try_into().unwrap());
+        // the count must account for the rest of the file exactly
+        if count.checked_mul(RECORD as u64) != Some((bytes.len() - header) as u64) {
+            return Err(invalid());
+        }
+        let (keys, entries) = bytes[header..].split_at(count as usize * 16);
+        let keys: Vec<_> = keys.chunks_exact(16).map(|key| u128::from_le_bytes(key.try_into().unwrap())).collect();
+        if keys.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(invalid());
+        }
+        let entries = entries.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+        Ok(Tablebase { rules, keys, entries })
+    }
+}
+
+static INSTALLED: OnceLock<Tablebase> = OnceLock::new();
+
+/// Makes the tablebase available to `probe` for the rest of the run; only the
+/// first one installed is kept.
+pub fn install(tablebase: Tablebase) {
+    let _ = INSTALLED.set(tablebase);
+}
+
+pub fn installed() -> Option<&'static Tablebase> {
+    INSTALLED.get()
+}
+
+/// Looks `board` up in the installed tablebase.
+pub fn probe(board: &Chessboard) -> Option<Value> {
+    INSTALLED.get()?.probe(board)
+}
+
+/// The ways to place as many stones as `start` has, each of either side, with
+/// either side to move, counting symmetric copies once. Games reach a good share
+/// of them, so a board with too many is not worth exploring.
+fn arrangements(start: &Chessboard) -> u128 {
+    let size = start.size();
+    let cells = (size * size) as u128;
+    let stones = start.chessboard[..size].iter().flat_map(|row| &row[..size]).filter(|&&chess| chess != Chess::Void).count() as u128;
+    let choose = (0..stones).fold(1_u128, |ways, i| ways * (cells - i) / (i + 1));
+    choose << stones << 1 >> 3
+}
+
+/// Passes so far as far as they can change the outcome: only two in a row can
+/// end the game.
+fn passes(board: &Chessboard) -> u128 {
+    if board.rules.pass && board.rules.double_pass_ends { board.passes.min(2) as u128 } else { 0 }
+}
+
+/// The cells of `board` two bits each (0 empty, 1 A, 2 B) row by row, then a
+/// byte of plies left of a move handicap, the passes so far and the side to move.
+fn pack(board: &Chessboard) -> u128 {
+    let size = board.size();
+    let mut key = 0;
+    for r in 0..size {
+        for c in 0..size {
+            key = key << 2
+                | match board.chessboard[r][c] {
+                    Chess::Void => 0,
+                    Chess::A => 1,
+                    Chess::B => 2,
+                };
+        }
+    }
+    let turn = match board.next_turn {
+        Turn::A => 0,
+        Turn::B => 1,
+    };
+    ((key << 8 | board.handicap_plies as u128) << 2 | passes(board)) << 1 | turn
+}
+
+/// The smallest packed key among `board` and its symmetric copies.
+fn key(board: &Chessboard) -> u128 {
+    Symmetry::all(board.size()).iter().map(|symmetry| pack(&symmetry.board(board))).min().unwrap()
+}
+
+/// The position packed into `key` under `rules`.
+fn unpack(rules: &Rules, key: u128) -> Chessboard {
+    let size = rules.size;
+    let mut board = Chessboard::start(*rules);
+    board.chessboard = [[Chess::Void; N]; N];
+    board.next_turn = if key & 1 == 0 { Turn::A } else { Turn::B };
+    board.passes = (key >> 1 & 3) as u8;
+    board.handicap_plies = (key >> 3 & 0xff) as u8;
+    let mut cells = key >> 11;
+    for cell in (0..size * size).rev() {
+        board.chessboard[cell / size][cell % size] = match cells & 3 {
+            1 => Chess::A,
+            2 => Chess::B,
+            _ => Chess::Void,
+        };
+        cells >>= 2;
+    }
+    board
+}
+
+/// The canonical keys of the positions reachable from `start` in the order they
+/// were found, the start first, and every move between them by position number.
+type Graph = (Vec<u128>, Vec<(u32, u32)>);
+
+/// Finds every position reachable from `start`, giving up past `max_positions`.
+fn explore(start: &Chessboard, max_positions: usize, mut progress: impl FnMut(usize)) -> Option<Graph> {
+    let mut keys = vec![key(start)];
+    let mut numbers = HashMap::from([(keys[0], 0_u32)]);
+    let mut successors = vec![];
+    let mut index = 0;
+    while index < keys.len() {
+        let board = unpack(&start.rules, keys[index]);
+        if let Status::Free(_) = board.check() {
+            for (r, c, op) in board.available() {
+                let child = key(&board.next(r, c, op).unwrap());
+                let number = *numbers.entry(child).or_insert_with(|| {
+                    keys.push(child);
+                    progress(keys.len());
+                    keys.len() as u32 - 1
+                });
+                successors.push((index as u32, number));
+            }
+            if keys.len() > max_positions {
+                return None;
+            }
+        }
+        index += 1;
+    }
+    Some((keys, successors))
+}
+
+const DRAW: u16 = 0;
+const WIN: u16 = 1;
+const LOSS: u16 = 2;
+
+fn encode(outcome: u16, plies: u32) -> u16 {
+    (plies.min(u16::MAX as u32 >> 2) as u16) << 2 | outcome
+}
+
+fn decode(entry: u16) -> Value {
+    let plies = (entry >> 2) as u32;
+    match entry & 3 {
+        WIN => Value::Win(plies),
+        LOSS => Value::Loss(plies),
+        _ => Value::Draw,
+    }
+}
+
+/// Retrograde analysis of the positions `keys` with the moves `successors`
+/// between them: the ends of the game are known, and a position is won once a
+/// move reaches a lost one, lost once every move reaches a won one. Whatever is
+/// left can be played forever.
+fn solve(rules: &Rules, keys: &[u128], successors: Vec<(u32, u32)>) -> Vec<u16> {
+    let count = keys.len();
+    let mut entries = vec![DRAW; count];
+    let mut known = vec![false; count];
+    let mut queue = VecDeque::new();
+    // moves left to refute for each open position, and whether one of them draws
+    let mut open = vec![0_u32; count];
+    let mut drawn = vec![false; count];
+    for &(parent, _) in &successors {
+        open[parent as usize] += 1;
+    }
+    for (index, &key) in keys.iter().enumerate() {
+        if open[index] > 0 {
+            continue;
+        }
+        let board = unpack(rules, key);
+        if let Status::Win(who) = board.check() {
+            entries[index] = encode(if who == board.next_turn { WIN } else { LOSS }, 0);
+        }
+        known[index] = true;
+        queue.push_back(index);
+    }
+    // the same moves reversed, as compressed rows
+    let mut starts = vec![0_usize; count + 1];
+    for &(_, child) in &successors {
+        starts[child as usize + 1] += 1;
+    }
+    for index in 0..count {
+        starts[index + 1] += starts[index];
+    }
+    let mut fill = starts.clone();
+    let mut predecessors = vec![0_u32; successors.len()];
+    for (parent, child) in successors {
+        predecessors[fill[child as usize]] = parent;
+        fill[child as usize] += 1;
+    }
+    while let Some(child) = queue.pop_front() {
+        let value = decode(entries[child]);
+        for &parent in &predecessors[starts[child]..starts[child + 1]] {
+            let parent = parent as usize;
+            if known[parent] {
+                continue;
+            }
+            match value {
+                Value::Loss(plies) => entries[parent] = encode(WIN, plies + 1),
+                Value::Win(plies) => {
+                    open[parent] -= 1;
+                    if open[parent] > 0 {
+                        continue;
+                    }
+                    entries[parent] = if drawn[parent] { DRAW } else { encode(LOSS, plies + 1) };
+                }
+                Value::Draw => {
+                    drawn[parent] = true;
+                    open[parent] -= 1;
+                    if open[parent] > 0 {
+                        continue;
+                    }
+                    entries[parent] = DRAW;
+                }
+            }
+            known[parent] = true;
+            queue.push_back(parent);
+        }
+    }
+    entries
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::handicap::{Handicap, Odds};
+
+    /// A 4x4 game where B starts with a single stone.
+    fn start() -> Chessboard {
+        let rules = Rules { size: 4, ..Rules::default() };
+        Chessboard::start(rules).with_handicap(&Handicap { stronger: Turn::A, odds: Odds::Stones(2) })
+    }
+
+    #[test]
+    fn covers_every_position_of_the_game() {
+        let start = start();
+        let tablebase = Tablebase::generate(&start, usize::MAX, |_| ()).unwrap();
+        // the best moves keep the value of every position they pass
+        let mut board = start.clone();
+        for _ in 0..50 {
+            let Status::Free(_) = board.check() else {
+                break;
+            };
+            let ((r, c, op), value) = tablebase.best(&board).unwrap();
+            assert_eq!(tablebase.probe(&board), Some(value));
+            board = board.next(r, c, op).unwrap();
+        }
+        assert!(Tablebase::generate(&start, 10, |_| ()).is_none());
+    }
+
+    #[test]
+    fn refuses_boards_too_big_to_solve() {
+        let rules = |size| Rules { size, ..Rules::default() };
+        assert!(arrangements(&Chessboard::start(rules(4))) < 1_000_000);
+        // 5x5 and 6x6 games would need hundreds of millions of positions or more
+        assert!(arrangements(&Chessboard::start(rules(5))) > 100_000_000);
+        let start = Chessboard::start(rules(N));
+        assert!(Tablebase::generate(&start, 10_000_000, |_| panic!("explored a board that cannot fit")).is_none());
+    }
+
+    #[test]
+    fn rejects_a_corrupt_count() {
+        let tablebase = Tablebase::generate(&start(), usize::MAX, |_| ()).unwrap();
+        let path = std::env::temp_dir().join(format!("myomyw-tablebase-{}.bin", std::process::id()));
+        tablebase.save(&path).unwrap();
+        assert_eq!(Tablebase::load(&path).unwrap().positions(), tablebase.positions());
+        let mut bytes = std::fs::read(&path).unwrap();
+        let count = MAGIC.len() + RULE_BYTES;
+        bytes[count..count + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&path, bytes).unwrap();
+        assert!(Tablebase::load(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+}
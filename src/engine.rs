@@ -0,0 +1,177 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::chess::tablebase;
+use crate::chess::tablebase::Value;
+use crate::chess::{Chessboard, Operation, Status, Turn};
+use crate::engine::alphabeta::AlphaBeta;
+use crate::engine::book::Opening;
+use crate::engine::features::Weights;
+use crate::engine::greedy::Greedy;
+use crate::engine::minimax::Minimax;
+use crate::engine::random::Random;
+use crate::mcts::{Leaf, Mcts, Policy};
+
+pub mod alphabeta;
+pub mod background;
+pub mod book;
+pub mod features;
+#[cfg(test)]
+pub mod fixtures;
+pub mod greedy;
+pub mod minimax;
+pub mod pns;
+pub mod random;
+
+/// Score of a won position; `evaluate` never reaches it otherwise.
+pub const WIN: i64 = 1e9 as i64;
+
+pub const NAMES: [&str; 7] = ["mcts", "mcts-rollout", "mcts-blend", "random", "greedy", "minimax", "alphabeta"];
+
+pub trait Engine: Send {
+    fn name(&self) -> &'static str;
+    /// Picks a move for the side to move, stopping early once `control` is stopped
+    /// and reporting the best move so far to it; the game must not be over, so
+    /// that there is a legal move.
+    fn search(&mut self, board: &Chessboard, limits: Limits, control: &Control) -> SearchResult;
+    /// Replaces the weights of the static evaluation, for engines that use it.
+    fn set_weights(&mut self, _weights: Weights) {}
+    /// Replaces the opening book played from before searching, for engines that
+    /// use one; `None` searches every position.
+    fn set_book(&mut self, _book: Option<Opening>) {}
+}
+
+/// Shared between a running search and whoever waits for it.
+#[derive(Default)]
+pub struct Control {
+    stop: AtomicBool,
+    progress: Mutex<Option<SearchResult>>,
+}
+
+impl Control {
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+    pub fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+    pub fn report(&self, result: SearchResult) {
+        *self.progress.lock().unwrap() = Some(result);
+    }
+    /// The latest result the search reported, if any.
+    pub fn progress(&self) -> Option<SearchResult> {
+        self.progress.lock().unwrap().clone()
+    }
+}
+
+/// How much work a search may do; engines use their own defaults for `None`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Limits {
+    /// MCTS playouts.
+    pub playouts: Option<u32>,
+    /// Search depth in plies.
+    pub depth: Option<u32>,
+    /// Positions visited.
+    pub nodes: Option<u64>,
+    /// Wall-clock time.
+    pub time: Option<Duration>,
+    /// Search threads, for engines that use more than one.
+    pub threads: Option<usize>,
+}
+
+/// Difficulty presets for the interactive game, from 1 (easiest) to `LEVELS`.
+pub const LEVELS: u32 = 4;
+
+pub fn level(level: u32) -> Limits {
+    let level = level.clamp(1, LEVELS);
+    Limits {
+        playouts: Some(10_u32.pow(level + 2)),
+        depth: Some(level * 2),
+        ..Limits::default()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    /// (r, c, op)
+    pub mov: (i32, i32, Operation),
+    /// Positions or playouts examined.
+    pub nodes: u64,
+    /// Score from the mover's point of view, if the engine keeps one.
+    pub score: Option<i64>,
+    /// The line the engine expects, starting with `mov`.
+    pub pv: Vec<(i32, i32, Operation)>,
+    /// Plies to a forced end of the game once the engine has proven one: positive
+    /// when the mover wins, negative when it loses.
+    pub mate: Option<i64>,
+    /// Root moves from most to least searched, if the engine keeps them.
+    pub candidates: Vec<Candidate>,
+}
+
+/// A root move and what the search learned about it.
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    /// (r, c, op)
+    pub mov: (i32, i32, Operation),
+    pub visits: u64,
+    /// Mean score from the mover's point of view.
+    pub mean: i64,
+    /// Share of the search the move was expected to get before any playout.
+    pub prior: f64,
+    /// The line the search expects, starting with `mov`.
+    pub pv: Vec<(i32, i32, Operation)>,
+    /// As for `SearchResult::mate`.
+    pub mate: Option<i64>,
+}
+
+pub fn by_name(name: &str) -> Option<Box<dyn Engine>> {
+    match name {
+        "mcts" => Some(Box::new(Mcts::new())),
+        "mcts-rollout" => Some(Box::new(Mcts::new().with_leaf(Leaf::Rollout(Policy::Random)))),
+        "mcts-blend" => Some(Box::new(Mcts::new().with_leaf(Leaf::Blend(Policy::Greedy, 0.5)))),
+        "random" => Some(Box::new(Random::new())),
+        "greedy" => Some(Box::new(Greedy::default())),
+        "minimax" => Some(Box::new(Minimax::default())),
+        "alphabeta" => Some(Box::new(AlphaBeta::new())),
+        _ => None,
+    }
+}
+
+/// Static score of `board` for `side`: a win or loss at the extremes, less the
+/// plies to it when the tablebase knows it, otherwise the weighted features in
+/// hundredths of a stone.
+pub fn evaluate(board: &Chessboard, side: &Turn, weights: &Weights) -> i64 {
+    match board.check() {
+        Status::Win(who) if who == *side => WIN,
+        Status::Win(_) => -WIN,
+        Status::Draw => 0,
+        Status::Free(turn) => match tablebase::probe(board) {
+            Some(value) => {
+                let score = known(value, 0);
+                if turn == *side { score } else { -score }
+            }
+            None => weights.score(board, side),
+        },
+    }
+}
+
+/// Score of a tablebase value for the side to move, `ply` plies into a search.
+pub fn known(value: Value, ply: usize) -> i64 {
+    match value {
+        Value::Win(plies) => WIN - (ply + plies as usize) as i64,
+        Value::Loss(plies) => -(WIN - (ply + plies as usize) as i64),
+        Value::Draw => 0,
+    }
+}
+
+/// Plays out `pv` from `board`, pairing each move with the board it leads to.
+pub fn line(board: &Chessboard, pv: &[(i32, i32, Operation)]) -> Vec<((i32, i32, Operation), Chessboard)> {
+    let mut board = board.clone();
+    pv.iter()
+        .map(|(r, c, op)| {
+            board = board.next(*r, *c, op.clone()).unwrap();
+            ((*r, *c, op.clone()), board.clone())
+        })
+        .collect()
+}
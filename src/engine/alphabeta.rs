@@ -0,0 +1,316 @@
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::Instant;
+
+use crate::chess::tablebase;
+use crate::chess::{move_index, Chessboard, Operation, Status, MOVES};
+use crate::engine::book::Opening;
+use crate::engine::features::Weights;
+use crate::engine::{evaluate, known, Control, Engine, Limits, SearchResult, WIN};
+
+/// Deepest iteration when no limit is given.
+const DEPTH: u32 = 8;
+const MAX_PLY: usize = 64;
+/// Transposition table slots; a power of two.
+const TABLE: usize = 1 << 20;
+/// Scores beyond this are wins or losses at a known distance.
+const MATE: i64 = WIN - MAX_PLY as i64;
+const INFINITY: i64 = WIN + 1;
+/// Nodes between checks of the time and node limits.
+const CHECK_EVERY: u64 = 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    key: u64,
+    depth: u32,
+    score: i64,
+    bound: Bound,
+    /// Index of the best move in `Chessboard::available`.
+    best: usize,
+}
+
+/// Negamax with alpha-beta pruning and iterative deepening, ordering moves by the
+/// transposition table, killer moves and the history heuristic.
+pub struct AlphaBeta {
+    weights: Weights,
+    book: Option<Opening>,
+    table: Vec<Option<Entry>>,
+    killers: Vec<[Option<(i32, i32, Operation)>; 2]>,
+    history: Vec<u64>,
+    nodes: u64,
+    max_nodes: Option<u64>,
+    deadline: Option<Instant>,
+    stopped: bool,
+    root_best: Option<(i32, i32, Operation)>,
+}
+
+impl Default for AlphaBeta {
+    fn default() -> Self {
+        AlphaBeta::new()
+    }
+}
+
+impl AlphaBeta {
+    pub fn new() -> AlphaBeta {
+        AlphaBeta {
+            weights: Weights::default(),
+            book: None,
+            table: vec![None; TABLE],
+            killers: vec![[None, None]; MAX_PLY],
+            history: vec![0; MOVES],
+            nodes: 0,
+            max_nodes: None,
+            deadline: None,
+            stopped: false,
+            root_best: None,
+        }
+    }
+
+    fn negamax(&mut self, board: &Chessboard, depth: u32, ply: usize, mut alpha: i64, mut beta: i64, control: &Control) -> i64 {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(CHECK_EVERY) {
+            self.stopped |= control.stopped()
+                || self.max_nodes.is_some_and(|max| self.nodes >= max) || self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        }
+        if self.stopped {
+            return 0;
+        }
+        match board.check() {
+            Status::Win(who) if who == *board.turn() => return WIN - ply as i64,
+            Status::Win(_) => return -(WIN - ply as i64),
+            Status::Draw => return 0,
+            Status::Free(_) => (),
+        }
+        if ply > 0 {
+            if let Some(value) = tablebase::probe(board) {
+                return known(value, ply);
+            }
+        }
+        let moves = board.available();
+        if depth == 0 || moves.is_empty() || ply + 1 >= MAX_PLY {
+            return evaluate(board, board.turn(), &self.weights);
+        }
+        let key = hash(board);
+        let slot = key as usize & (TABLE - 1);
+        let alpha_orig = alpha;
+        let mut table_best = None;
+        if let Some(entry) = self.table[slot].filter(|entry| entry.key == key) {
+            table_best = Some(entry.best);
+            if entry.depth >= depth && ply > 0 {
+                let score = from_table(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower => alpha = alpha.max(score),
+                    Bound::Upper => beta = beta.min(score),
+                }
+                if alpha >= beta {
+                    return score;
+                }
+            }
+        }
+        let mut order: Vec<usize> = (0..moves.len()).collect();
+        order.sort_by_cached_key(|&i| Reverse(self.priority(&moves[i], Some(i) == table_best, ply)));
+        let mut best = (-INFINITY, order[0]);
+        for i in order {
+            let (r, c, op) = &moves[i];
+            let score = -self.negamax(&board.next(*r, *c, op.clone()).unwrap(), depth - 1, ply + 1, -beta, -alpha, control);
+            if self.stopped {
+                return 0;
+            }
+            if score > best.0 {
+                best = (score, i);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                let killers = &mut self.killers[ply];
+                if killers[0].as_ref() != Some(&moves[i]) {
+                    killers[1] = killers[0].take();
+                    killers[0] = Some(moves[i].clone());
+                }
+                self.history[move_index(&moves[i])] += (depth * depth) as u64;
+                break;
+            }
+        }
+        let bound = if best.0 <= alpha_orig {
+            Bound::Upper
+        } else if best.0 >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table[slot] = Some(Entry { key, depth, score: to_table(best.0, ply), bound, best: best.1 });
+        if ply == 0 {
+            self.root_best = Some(moves[best.1].clone());
+        }
+        best.0
+    }
+
+    /// The root move followed by the best move the table holds for each position
+    /// after it, up to `depth` moves.
+    fn pv(&self, board: &Chessboard, mov: (i32, i32, Operation), depth: u32) -> Vec<(i32, i32, Operation)> {
+        let mut board = board.next(mov.0, mov.1, mov.2.clone()).unwrap();
+        let mut line = vec![mov];
+        while line.len() < depth as usize && matches!(board.check(), Status::Free(_)) {
+            let key = hash(&board);
+            let Some(entry) = self.table[key as usize & (TABLE - 1)].filter(|entry| entry.key == key) else {
+                break;
+            };
+            let Some((r, c, op)) = board.available().get(entry.best).cloned() else {
+                break;
+            };
+            board = board.next(r, c, op.clone()).unwrap();
+            line.push((r, c, op));
+        }
+        line
+    }
+
+    fn priority(&self, mov: &(i32, i32, Operation), table_best: bool, ply: usize) -> u64 {
+        if table_best {
+            u64::MAX
+        } else if self.killers[ply][0].as_ref() == Some(mov) {
+            u64::MAX - 1
+        } else if self.killers[ply][1].as_ref() == Some(mov) {
+            u64::MAX - 2
+        } else {
+            self.history[move_index(mov)]
+        }
+    }
+}
+
+impl Engine for AlphaBeta {
+    fn name(&self) -> &'static str {
+        "alphabeta"
+    }
+
+    fn search(&mut self, board: &Chessboard, limits: Limits, control: &Control) -> SearchResult {
+        if let Some(result) = self.book.as_ref().and_then(|(book, choice)| book.play(board, *choice)) {
+            return result;
+        }
+        self.nodes = 0;
+        self.max_nodes = limits.nodes;
+        self.deadline = limits.time.map(|time| Instant::now() + time);
+        self.stopped = false;
+        self.killers.iter_mut().for_each(|killers| *killers = [None, None]);
+        let max_depth = match limits {
+            Limits { depth: Some(depth), .. } => depth,
+            Limits { nodes: None, time: None, .. } => DEPTH,
+            _ => MAX_PLY as u32 - 1,
+        };
+        let mut result = None;
+        for depth in 1..=max_depth.max(1) {
+            self.root_best = None;
+            let score = self.negamax(board, depth, 0, -INFINITY, INFINITY, control);
+            if self.stopped {
+                break;
+            }
+            result = self.root_best.take().map(|mov| (score, self.pv(board, mov, depth)));
+            if let Some((score, pv)) = &result {
+                control.report(SearchResult {
+                    mov: pv[0].clone(),
+                    nodes: self.nodes,
+                    score: Some(*score),
+                    pv: pv.clone(),
+                    mate: mate(*score),
+                    candidates: vec![],
+                });
+            }
+            if score.abs() > MATE {
+                break;
+            }
+        }
+        let (score, pv) = match result {
+            Some((score, pv)) => (Some(score), pv),
+            // stopped before the first iteration finished
+            None => (None, vec![board.available().swap_remove(0)]),
+        };
+        SearchResult { mov: pv[0].clone(), nodes: self.nodes, score, pv, mate: score.and_then(mate), candidates: vec![] }
+    }
+
+    fn set_weights(&mut self, weights: Weights) {
+        if weights != self.weights {
+            // stored scores were computed with the old weights
+            self.table.iter_mut().for_each(|entry| *entry = None);
+        }
+        self.weights = weights;
+    }
+
+    fn set_book(&mut self, book: Option<Opening>) {
+        self.book = book;
+    }
+}
+
+fn hash(board: &Chessboard) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    board.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Plies to the end for a win or loss score.
+fn mate(score: i64) -> Option<i64> {
+    if score > MATE {
+        Some(WIN - score)
+    } else if score < -MATE {
+        Some(-(WIN + score))
+    } else {
+        None
+    }
+}
+
+/// Win and loss scores are stored relative to the node, not the root.
+fn to_table(score: i64, ply: usize) -> i64 {
+    if score > MATE {
+        score + ply as i64
+    } else if score < -MATE {
+        score - ply as i64
+    } else {
+        score
+    }
+}
+
+fn from_table(score: i64, ply: usize) -> i64 {
+    if score > MATE {
+        score - ply as i64
+    } else if score < -MATE {
+        score + ply as i64
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::Rules;
+    use crate::engine::fixtures::{double_threat, mate_in_one};
+
+    fn score(board: &Chessboard, depth: u32) -> SearchResult {
+        let limits = Limits { depth: Some(depth), ..Limits::default() };
+        AlphaBeta::new().search(board, limits, &Control::default())
+    }
+
+    #[test]
+    fn finds_a_win_in_one() {
+        let result = score(&mate_in_one(), 3);
+        assert_eq!(result.mov, (1, 1, Operation::Up));
+        assert_eq!(result.score, Some(WIN - 1));
+    }
+
+    #[test]
+    fn scores_a_lost_position_for_the_side_to_move() {
+        assert_eq!(score(&double_threat(Rules::default()), 4).score, Some(-(WIN - 2)));
+    }
+
+    #[test]
+    fn passing_does_not_escape_a_double_threat() {
+        let rules = Rules { pass: true, double_pass_ends: false, ..Rules::default() };
+        assert_eq!(score(&double_threat(rules), 4).score, Some(-(WIN - 2)));
+    }
+}
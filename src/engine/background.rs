@@ -0,0 +1,43 @@
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use crate::chess::Chessboard;
+use crate::engine::{Control, Engine, Limits, SearchResult};
+
+/// A search running on its own thread; the engine is handed back when it ends.
+pub struct Background {
+    control: Arc<Control>,
+    thread: JoinHandle<(Box<dyn Engine>, SearchResult)>,
+}
+
+impl Background {
+    pub fn spawn(mut engine: Box<dyn Engine>, board: Chessboard, limits: Limits) -> Background {
+        let control = Arc::new(Control::default());
+        let shared = control.clone();
+        let thread = std::thread::spawn(move || {
+            let result = engine.search(&board, limits, &shared);
+            (engine, result)
+        });
+        Background { control, thread }
+    }
+
+    /// The best move found so far, with the search statistics behind it.
+    pub fn progress(&self) -> Option<SearchResult> {
+        self.control.progress()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Stops the search and waits for it to return its best move.
+    pub fn stop(self) -> (Box<dyn Engine>, SearchResult) {
+        self.control.stop();
+        self.wait()
+    }
+
+    /// Waits for the search to reach its limits.
+    pub fn wait(self) -> (Box<dyn Engine>, SearchResult) {
+        self.thread.join().unwrap()
+    }
+}
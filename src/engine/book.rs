@@ -0,0 +1,219 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::chess::{Chessboard, Operation, Rules, Status};
+use crate::engine::{Control, Engine, Limits, SearchResult};
+use crate::record::{from_notation, notation};
+
+/// First line of a book file; the number is the format version.
+const HEADER: &str = "myomyw-book";
+const VERSION: u32 = 3;
+/// Searched moves kept per position, if they got enough of the search.
+const WIDTH: usize = 3;
+/// Share of the best move's visits another move needs to be kept.
+const KEEP: f64 = 0.25;
+
+/// (r, c, op) and its weight.
+type BookMove = ((i32, i32, Operation), u64);
+
+/// A book that engines can share, and how they pick from it.
+pub type Opening = (Arc<Book>, Choice);
+
+/// Moves for the opening, keyed by canonical position so that symmetric
+/// positions share their entry.
+pub struct Book {
+    rules: Rules,
+    /// Moves as played on the canonical board, with their weights.
+    entries: HashMap<String, Vec<BookMove>>,
+}
+
+/// How a move is picked from the book.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Choice {
+    /// The move with the most weight.
+    Best,
+    /// Any move, as likely as its share of the weight.
+    Weighted,
+}
+
+/// Why a book file could not be read; lines are 1-based.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    BadHeader,
+    UnsupportedVersion(u32),
+    BadLine(usize),
+}
+
+impl Book {
+    /// Searches every position up to `plies` into the game and keeps the moves
+    /// the search liked, following each of them; `progress` hears of every
+    /// position searched.
+    pub fn build(rules: Rules, plies: usize, engine: &mut dyn Engine, limits: Limits, mut progress: impl FnMut(usize)) -> Book {
+        let mut book = Book { rules, entries: HashMap::new() };
+        let mut queue = VecDeque::from([(Chessboard::start(rules), 0)]);
+        while let Some((board, ply)) = queue.pop_front() {
+            let (key, symmetry) = board.canonical();
+            if ply >= plies || !matches!(board.check(), Status::Free(_)) || book.entries.contains_key(&key) {
+                continue;
+            }
+            let result = engine.search(&board, limits, &Control::default());
+            let moves = chosen(&result);
+            for (r, c, op) in moves.iter().map(|(mov, _)| mov.clone()) {
+                queue.push_back((board.next(r, c, op).unwrap(), ply + 1));
+            }
+            book.entries.insert(key, moves.into_iter().map(|(mov, weight)| (symmetry.mov(&mov), weight)).collect());
+            progress(book.entries.len());
+        }
+        book
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn positions(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The book moves for `board` and their weights, if it is in the book.
+    pub fn moves(&self, board: &Chessboard) -> Option<Vec<BookMove>> {
+        if *board.rules() != self.rules {
+            return None;
+        }
+        let (key, symmetry) = board.canonical();
+        let entry = self.entries.get(&key)?;
+        let moves: Vec<_> = board
+            .available()
+            .into_iter()
+            .filter_map(|mov| {
+                let (_, weight) = entry.iter().find(|(book, _)| *book == symmetry.mov(&mov))?;
+                Some((mov, *weight))
+            })
+            .collect();
+        (!moves.is_empty()).then_some(moves)
+    }
+
+    pub fn choose(&self, board: &Chessboard, choice: Choice) -> Option<(i32, i32, Operation)> {
+        let moves = self.moves(board)?;
+        match choice {
+            Choice::Best => moves.into_iter().max_by_key(|(_, weight)| *weight).map(|(mov, _)| mov),
+            Choice::Weighted => {
+                let total: u64 = moves.iter().map(|(_, weight)| weight).sum();
+                let mut pick = rand::rng().random_range(0..total);
+                for (mov, weight) in moves {
+                    if pick < weight {
+                        return Some(mov);
+                    }
+                    pick -= weight;
+                }
+                None
+            }
+        }
+    }
+
+    /// A move from the book as a search result, for engines to play without
+    /// searching.
+    pub fn play(&self, board: &Chessboard, choice: Choice) -> Option<SearchResult> {
+        let mov = self.choose(board, choice)?;
+        Some(SearchResult { pv: vec![mov.clone()], mov, nodes: 0, score: None, mate: None, candidates: vec![] })
+    }
+
+    /// Reads the format written by `Display`.
+    pub fn parse(text: &str) -> Result<Book, ParseError> {
+        let mut lines = text.lines().enumerate();
+        let version = match lines.next().and_then(|(_, line)| line.split_once(' ')) {
+            Some((HEADER, version)) => version.trim().parse().map_err(|_| ParseError::BadHeader)?,
+            _ => return Err(ParseError::BadHeader),
+        };
+        if version != VERSION {
+            return Err(ParseError::UnsupportedVersion(version));
+        }
+        let bytes: Option<Vec<u8>> = match lines.next() {
+            Some((_, line)) => line.strip_prefix("rules ").map(|rules| rules.split_whitespace().map(|byte| byte.parse().ok()).collect()),
+            None => None,
+        }
+        .flatten();
+        let rules = bytes.and_then(|bytes| Rules::from_bytes(&bytes)).ok_or(ParseError::BadLine(2))?;
+        let mut entries: HashMap<_, Vec<_>> = HashMap::new();
+        for (i, line) in lines {
+            let bad = || ParseError::BadLine(i + 1);
+            let mut parts = line.splitn(3, ' ');
+            let (Some(key), Some(weight), Some(mov)) = (parts.next(), parts.next(), parts.next()) else {
+                return Err(bad());
+            };
+            let weight = weight.parse().ok().filter(|&weight| weight > 0).ok_or_else(bad)?;
+            let mov = from_notation(mov).ok_or_else(bad)?;
+            entries.entry(key.to_string()).or_default().push((mov, weight));
+        }
+        Ok(Book { rules, entries })
+    }
+}
+
+/// The searched move and the runners-up close enough to it, weighted by their
+/// visits when the engine keeps them.
+fn chosen(result: &SearchResult) -> Vec<BookMove> {
+    let Some(best) = result.candidates.iter().find(|candidate| candidate.mov == result.mov) else {
+        return vec![(result.mov.clone(), 1)];
+    };
+    let mut moves = vec![(best.mov.clone(), best.visits.max(1))];
+    moves.extend(
+        result.candidates
+            .iter()
+            .filter(|candidate| candidate.mov != best.mov && candidate.visits > 0 && candidate.visits as f64 >= best.visits as f64 * KEEP)
+            .take(WIDTH - 1)
+            .map(|candidate| (candidate.mov.clone(), candidate.visits)),
+    );
+    moves
+}
+
+/// The header, the rules as numbers, then `key weight move` for each book move.
+impl Display for Book {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", HEADER, VERSION)?;
+        let rules: Vec<_> = self.rules.to_bytes().iter().map(u8::to_string).collect();
+        writeln!(f, "rules {}", rules.join(" "))?;
+        let mut keys: Vec<_> = self.entries.keys().collect();
+        keys.sort();
+        for key in keys {
+            for (mov, weight) in &self.entries[key] {
+                writeln!(f, "{} {} {}", key, weight, notation(mov))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ParseError::BadHeader => write!(f, "not an opening book"),
+            ParseError::UnsupportedVersion(version) => write!(f, "unsupported book version {}", version),
+            ParseError::BadLine(line) => write!(f, "line {}: expected a position, a positive weight and a move", line),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let board = Chessboard::start(Rules::default());
+        let (key, symmetry) = board.canonical();
+        let mov = board.available().swap_remove(0);
+        let book = Book { rules: Rules::default(), entries: HashMap::from([(key, vec![(symmetry.mov(&mov), 3)])]) };
+        let text = book.to_string();
+        let read = Book::parse(&text).unwrap();
+        assert_eq!(read.to_string(), text);
+        assert_eq!(read.moves(&board), Some(vec![(mov, 3)]));
+        // a move with no weight could never be picked
+        assert_eq!(Book::parse(&text.replacen(" 3 ", " 0 ", 1)).err(), Some(ParseError::BadLine(3)));
+    }
+}
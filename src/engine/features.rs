@@ -0,0 +1,202 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::chess::rules::{count_cross, count_snake, Tense};
+use crate::chess::{Chess, Chessboard, Operation, Turn, N};
+
+/// Feature names, in the order they are written to a weights file.
+const FEATURES: [&str; 6] = ["stones", "threats", "stuck", "mobility", "clustering", "edges"];
+
+/// What one unit of each feature is worth to the side that has it, in stones.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Weights {
+    /// Stones on the board.
+    pub stones: f64,
+    /// Moves that leave the mover with fewer stones once the snakes have flipped.
+    pub threats: f64,
+    /// Stones held in crosses whose snakes pull both ways and so never flip.
+    pub stuck: f64,
+    /// Legal moves, not counting a pass.
+    pub mobility: f64,
+    /// Pairs of own stones side by side.
+    pub clustering: f64,
+    /// Stones on the edge of the board.
+    pub edges: f64,
+}
+
+/// Why a weights file could not be read; lines are 1-based.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    UnknownFeature(usize, String),
+    BadWeight(usize),
+}
+
+/// Counts only the stones, as the engines always did.
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            stones: -1.0,
+            threats: 0.0,
+            stuck: 0.0,
+            mobility: 0.0,
+            clustering: 0.0,
+            edges: 0.0,
+        }
+    }
+}
+
+impl Weights {
+    /// Reads `feature weight` lines; `#` starts a comment and features left out
+    /// keep their default weight.
+    pub fn parse(text: &str) -> Result<Weights, ParseError> {
+        let mut weights = Weights::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, value) = line.split_once(char::is_whitespace).ok_or(ParseError::BadWeight(i + 1))?;
+            let value = value.trim().parse().map_err(|_| ParseError::BadWeight(i + 1))?;
+            *weights.get_mut(name).ok_or_else(|| ParseError::UnknownFeature(i + 1, name.to_string()))? = value;
+        }
+        Ok(weights)
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut f64> {
+        match name {
+            "stones" => Some(&mut self.stones),
+            "threats" => Some(&mut self.threats),
+            "stuck" => Some(&mut self.stuck),
+            "mobility" => Some(&mut self.mobility),
+            "clustering" => Some(&mut self.clustering),
+            "edges" => Some(&mut self.edges),
+            _ => None,
+        }
+    }
+
+    fn get(&self, name: &str) -> f64 {
+        let mut weights = *self;
+        *weights.get_mut(name).unwrap()
+    }
+
+    /// Weighted features of `side` less those of its opponent, in hundredths of a
+    /// stone; features with no weight are not computed.
+    pub fn score(&self, board: &Chessboard, side: &Turn) -> i64 {
+        let opponent = match side {
+            Turn::A => Turn::B,
+            Turn::B => Turn::A,
+        };
+        let mut score = 0.0;
+        if self.stones != 0.0 {
+            let (num_a, num_b) = board.state();
+            let diff = (num_a - num_b) as f64;
+            score += self.stones * if *side == Turn::A { diff } else { -diff };
+        }
+        if self.threats != 0.0 {
+            score += self.threats * (threats(board, side) - threats(board, &opponent)) as f64;
+        }
+        if self.stuck != 0.0 {
+            let (a, b) = stuck(board);
+            score += self.stuck * if *side == Turn::A { a - b } else { b - a } as f64;
+        }
+        if self.mobility != 0.0 {
+            score += self.mobility * (mobility(board, side) - mobility(board, &opponent)) as f64;
+        }
+        if self.clustering != 0.0 {
+            score += self.clustering * (count(board, side, clustered) - count(board, &opponent, clustered)) as f64;
+        }
+        if self.edges != 0.0 {
+            score += self.edges * (count(board, side, on_edge) - count(board, &opponent, on_edge)) as f64;
+        }
+        (score * 100.0).round() as i64
+    }
+}
+
+fn stone(side: &Turn) -> Chess {
+    match side {
+        Turn::A => Chess::A,
+        Turn::B => Chess::B,
+    }
+}
+
+fn own_stones(board: &Chessboard, side: &Turn) -> i32 {
+    let (num_a, num_b) = board.state();
+    if *side == Turn::A { num_a } else { num_b }
+}
+
+fn threats(board: &Chessboard, side: &Turn) -> i32 {
+    let board = board.clone().with_turn(side.clone());
+    let before = own_stones(&board, side);
+    board
+        .available()
+        .into_iter()
+        .filter(|(_, _, op)| *op != Operation::Pass)
+        .filter(|(r, c, op)| board.next(*r, *c, op.clone()).is_ok_and(|next| own_stones(&next, side) < before))
+        .count() as i32
+}
+
+/// Stones of A and B inside crosses holding snakes of both tenses.
+fn stuck(board: &Chessboard) -> (i32, i32) {
+    let snakes = count_snake(board);
+    let mut cells = vec![];
+    for cross in count_cross(&snakes) {
+        let tenses = |tense: Tense| cross.snakes().iter().any(|snake| *snake.tense() == tense);
+        if tenses(Tense::A) && tenses(Tense::B) {
+            cells.extend(cross.snakes().iter().flat_map(|snake| snake.cells()));
+        }
+    }
+    cells.sort();
+    cells.dedup();
+    cells.into_iter().fold((0, 0), |(a, b), (r, c)| match board.at(r, c) {
+        Chess::A => (a + 1, b),
+        Chess::B => (a, b + 1),
+        Chess::Void => (a, b),
+    })
+}
+
+fn mobility(board: &Chessboard, side: &Turn) -> i32 {
+    board.clone().with_turn(side.clone()).available().iter().filter(|(_, _, op)| *op != Operation::Pass).count() as i32
+}
+
+/// How many cells of `side` pass `test`.
+fn count(board: &Chessboard, side: &Turn, test: fn(&Chessboard, usize, usize) -> i32) -> i32 {
+    let own = stone(side);
+    let size = board.size();
+    (0..size)
+        .flat_map(|r| (0..size).map(move |c| (r, c)))
+        .filter(|&(r, c)| board.at(r, c) == own)
+        .map(|(r, c)| test(board, r, c))
+        .sum()
+}
+
+/// Own neighbours below and to the right, so each pair counts once.
+fn clustered(board: &Chessboard, r: usize, c: usize) -> i32 {
+    let own = board.at(r, c);
+    (r + 1 < N && board.at(r + 1, c) == own) as i32 + (c + 1 < N && board.at(r, c + 1) == own) as i32
+}
+
+fn on_edge(board: &Chessboard, r: usize, c: usize) -> i32 {
+    let last = board.size() - 1;
+    (r == 0 || c == 0 || r == last || c == last) as i32
+}
+
+/// One `feature weight` line per feature, readable by `Weights::parse`.
+impl Display for Weights {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for name in FEATURES {
+            writeln!(f, "{} {}", name, self.get(name))?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnknownFeature(line, name) => write!(f, "line {}: unknown feature {}", line, name),
+            ParseError::BadWeight(line) => write!(f, "line {}: expected a feature and a number", line),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
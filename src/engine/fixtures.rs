@@ -0,0 +1,17 @@
+use crate::chess::{Chessboard, Rules, Turn};
+
+/// A wins by moving up between the two B stones, turning its last stone.
+pub fn mate_in_one() -> Chessboard {
+    Chessboard::from_rows(Rules::default(), &["B.B", ".A."])
+}
+
+/// The same threat with B to move: moving either stone beside the gap defends,
+/// moving the far stone loses at once.
+pub fn threat() -> Chessboard {
+    Chessboard::from_rows(Rules::default(), &["B.B", ".A.", "", "", "", ".....B"]).with_turn(Turn::B)
+}
+
+/// B to move while A threatens to win both above and below its stone.
+pub fn double_threat(rules: Rules) -> Chessboard {
+    Chessboard::from_rows(rules, &["", ".B.B", "..A", ".B.B"]).with_turn(Turn::B)
+}
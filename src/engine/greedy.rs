@@ -0,0 +1,31 @@
+use crate::chess::Chessboard;
+use crate::engine::features::Weights;
+use crate::engine::{evaluate, Control, Engine, Limits, SearchResult};
+
+/// Plays the move with the best static score one ply ahead.
+#[derive(Default)]
+pub struct Greedy {
+    weights: Weights,
+}
+
+impl Engine for Greedy {
+    fn name(&self) -> &'static str {
+        "greedy"
+    }
+
+    fn search(&mut self, board: &Chessboard, _limits: Limits, _control: &Control) -> SearchResult {
+        let turn = board.turn();
+        let moves = board.available();
+        let nodes = moves.len() as u64;
+        let (score, mov) = moves
+            .into_iter()
+            .map(|(r, c, op)| (evaluate(&board.next(r, c, op.clone()).unwrap(), turn, &self.weights), (r, c, op)))
+            .max_by_key(|(score, _)| *score)
+            .unwrap();
+        SearchResult { pv: vec![mov.clone()], mov, nodes, score: Some(score), mate: None, candidates: vec![] }
+    }
+
+    fn set_weights(&mut self, weights: Weights) {
+        self.weights = weights;
+    }
+}
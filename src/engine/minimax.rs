@@ -0,0 +1,70 @@
+use crate::chess::{Chessboard, Operation, Status};
+use crate::engine::book::Opening;
+use crate::engine::features::Weights;
+use crate::engine::{evaluate, Control, Engine, Limits, SearchResult, WIN};
+
+const DEPTH: u32 = 3;
+
+/// Full-width negamax to a fixed depth over the static score.
+#[derive(Default)]
+pub struct Minimax {
+    weights: Weights,
+    book: Option<Opening>,
+}
+
+impl Engine for Minimax {
+    fn name(&self) -> &'static str {
+        "minimax"
+    }
+
+    fn search(&mut self, board: &Chessboard, limits: Limits, control: &Control) -> SearchResult {
+        if let Some(result) = self.book.as_ref().and_then(|(book, choice)| book.play(board, *choice)) {
+            return result;
+        }
+        let depth = limits.depth.unwrap_or(DEPTH).max(1);
+        let mut nodes = 0;
+        let mut best = None;
+        for (r, c, op) in board.available() {
+            if control.stopped() && best.is_some() {
+                break;
+            }
+            let next = board.next(r, c, op.clone()).unwrap();
+            let (score, mut pv) = negamax(&next, depth - 1, &self.weights, &mut nodes);
+            if best.as_ref().is_none_or(|(best, _)| -score > *best) {
+                pv.insert(0, (r, c, op));
+                best = Some((-score, pv));
+            }
+        }
+        let (score, pv) = best.unwrap();
+        SearchResult { mov: pv[0].clone(), nodes, score: Some(score), pv, mate: None, candidates: vec![] }
+    }
+
+    fn set_weights(&mut self, weights: Weights) {
+        self.weights = weights;
+    }
+
+    fn set_book(&mut self, book: Option<Opening>) {
+        self.book = book;
+    }
+}
+
+/// Score for the side to move and the line behind it; wins found with more depth
+/// left rank higher.
+fn negamax(board: &Chessboard, depth: u32, weights: &Weights, nodes: &mut u64) -> (i64, Vec<(i32, i32, Operation)>) {
+    *nodes += 1;
+    let moves = board.available();
+    if depth == 0 || moves.is_empty() || !matches!(board.check(), Status::Free(_)) {
+        let score = evaluate(board, board.turn(), weights);
+        let score = if score.abs() == WIN { score.signum() * (WIN + depth as i64) } else { score };
+        return (score, vec![]);
+    }
+    let mut best: Option<(i64, Vec<_>)> = None;
+    for (r, c, op) in moves {
+        let (score, mut pv) = negamax(&board.next(r, c, op.clone()).unwrap(), depth - 1, weights, nodes);
+        if best.as_ref().is_none_or(|(best, _)| -score > *best) {
+            pv.insert(0, (r, c, op));
+            best = Some((-score, pv));
+        }
+    }
+    best.unwrap()
+}
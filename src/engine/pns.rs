@@ -0,0 +1,176 @@
+use crate::chess::{Chessboard, Operation, Status, Turn};
+use crate::engine::Control;
+
+/// Proof and disproof numbers this large stand for infinity.
+const INFINITY: u64 = u64::MAX / 2;
+/// A win must come within this many plies; longer lines count as failures.
+pub const MAX_PLIES: usize = 64;
+
+pub enum Outcome {
+    /// The side to move wins by this line whatever the defence; the defending
+    /// moves in it are examples.
+    Win(Vec<(i32, i32, Operation)>),
+    /// The side to move cannot force a win within `MAX_PLIES`.
+    NoWin,
+    /// The node budget ran out or the search was stopped first.
+    Unknown,
+}
+
+struct Node {
+    board: Chessboard,
+    /// (r, c, op) of the move leading here.
+    mov: Option<(i32, i32, Operation)>,
+    parent: Option<usize>,
+    depth: usize,
+    children: Vec<usize>,
+    proof: u64,
+    disproof: u64,
+}
+
+/// Proof-number search for a forced win of the side to move, expanding at most
+/// `max_nodes` positions; also returns how many it expanded.
+pub fn solve(board: &Chessboard, max_nodes: u64, control: &Control) -> (Outcome, u64) {
+    let mut search = Search { attacker: board.turn().clone(), nodes: vec![] };
+    search.add(board.clone(), None, None, 0);
+    while search.nodes[0].proof != 0 && search.nodes[0].disproof != 0 {
+        if search.nodes.len() as u64 >= max_nodes || control.stopped() {
+            return (Outcome::Unknown, search.nodes.len() as u64);
+        }
+        let leaf = search.most_proving();
+        search.expand(leaf);
+        search.update(leaf);
+    }
+    let outcome = match search.nodes[0].proof {
+        0 => Outcome::Win(search.line()),
+        _ => Outcome::NoWin,
+    };
+    (outcome, search.nodes.len() as u64)
+}
+
+struct Search {
+    attacker: Turn,
+    nodes: Vec<Node>,
+}
+
+impl Search {
+    fn add(&mut self, board: Chessboard, mov: Option<(i32, i32, Operation)>, parent: Option<usize>, depth: usize) -> usize {
+        let (proof, disproof) = match board.check() {
+            Status::Win(who) if who == self.attacker => (0, INFINITY),
+            Status::Win(_) | Status::Draw => (INFINITY, 0),
+            Status::Free(_) if depth >= MAX_PLIES => (INFINITY, 0),
+            Status::Free(_) => (1, 1),
+        };
+        self.nodes.push(Node { board, mov, parent, depth, children: vec![], proof, disproof });
+        self.nodes.len() - 1
+    }
+
+    /// The attacker chooses at its own nodes and must answer every move at the others.
+    fn attacking(&self, index: usize) -> bool {
+        *self.nodes[index].board.turn() == self.attacker
+    }
+
+    /// Follows the smallest proof number at attacking nodes and the smallest
+    /// disproof number at defending ones down to an unexpanded node.
+    fn most_proving(&self) -> usize {
+        let mut index = 0;
+        while !self.nodes[index].children.is_empty() {
+            let children = &self.nodes[index].children;
+            index = if self.attacking(index) {
+                *children.iter().min_by_key(|&&child| self.nodes[child].proof).unwrap()
+            } else {
+                *children.iter().min_by_key(|&&child| self.nodes[child].disproof).unwrap()
+            };
+        }
+        index
+    }
+
+    fn expand(&mut self, index: usize) {
+        let board = self.nodes[index].board.clone();
+        let depth = self.nodes[index].depth + 1;
+        let children: Vec<_> = board
+            .available()
+            .into_iter()
+            .map(|(r, c, op)| {
+                let next = board.next(r, c, op.clone()).unwrap();
+                self.add(next, Some((r, c, op)), Some(index), depth)
+            })
+            .collect();
+        if children.is_empty() {
+            // no legal move: the attacker cannot win from here
+            self.nodes[index].proof = INFINITY;
+            self.nodes[index].disproof = 0;
+        }
+        self.nodes[index].children = children;
+    }
+
+    /// Recomputes the numbers from `index` up to the root, stopping where they
+    /// no longer change.
+    fn update(&mut self, mut index: usize) {
+        loop {
+            let node = &self.nodes[index];
+            if !node.children.is_empty() {
+                let proofs: Vec<_> = node.children.iter().map(|&child| self.nodes[child].proof).collect();
+                let disproofs: Vec<_> = node.children.iter().map(|&child| self.nodes[child].disproof).collect();
+                let (proof, disproof) = if self.attacking(index) {
+                    (*proofs.iter().min().unwrap(), sum(&disproofs))
+                } else {
+                    (sum(&proofs), *disproofs.iter().min().unwrap())
+                };
+                if (proof, disproof) == (node.proof, node.disproof) && index != 0 {
+                    return;
+                }
+                self.nodes[index].proof = proof;
+                self.nodes[index].disproof = disproof;
+            }
+            match self.nodes[index].parent {
+                Some(parent) => index = parent,
+                None => return,
+            }
+        }
+    }
+
+    /// A proven line from the root: a winning move at each attacking node and the
+    /// first proven reply at each defending one.
+    fn line(&self) -> Vec<(i32, i32, Operation)> {
+        let mut line = vec![];
+        let mut index = 0;
+        while let Some(&child) = self.nodes[index].children.iter().find(|&&child| self.nodes[child].proof == 0) {
+            line.push(self.nodes[child].mov.clone().unwrap());
+            index = child;
+        }
+        line
+    }
+}
+
+fn sum(numbers: &[u64]) -> u64 {
+    numbers.iter().fold(0, |sum: u64, &n| sum.saturating_add(n)).min(INFINITY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::Rules;
+    use crate::engine::fixtures::{double_threat, mate_in_one};
+
+    fn solved(board: &Chessboard) -> Outcome {
+        solve(board, 100_000, &Control::default()).0
+    }
+
+    #[test]
+    fn proves_a_win_in_one() {
+        match solved(&mate_in_one()) {
+            Outcome::Win(line) => assert_eq!(line, vec![(1, 1, Operation::Up)]),
+            _ => panic!("no win found"),
+        }
+    }
+
+    #[test]
+    fn proves_every_defence_loses() {
+        let board = double_threat(Rules::default());
+        assert!(matches!(solved(&board), Outcome::NoWin));
+        for (r, c, op) in board.available() {
+            let reply = board.next(r, c, op).unwrap();
+            assert!(matches!(solved(&reply), Outcome::Win(line) if line.len() == 1));
+        }
+    }
+}
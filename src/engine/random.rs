@@ -0,0 +1,42 @@
+use rand::rngs::SmallRng;
+use rand::seq::IndexedRandom;
+use rand::SeedableRng;
+
+use crate::chess::Chessboard;
+use crate::engine::{Control, Engine, Limits, SearchResult};
+
+/// Plays a uniformly random legal move.
+pub struct Random {
+    rng: SmallRng,
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Random::new()
+    }
+}
+
+impl Random {
+    pub fn new() -> Random {
+        Random { rng: SmallRng::from_os_rng() }
+    }
+}
+
+impl Engine for Random {
+    fn name(&self) -> &'static str {
+        "random"
+    }
+
+    fn search(&mut self, board: &Chessboard, _limits: Limits, _control: &Control) -> SearchResult {
+        let moves = board.available();
+        let mov = moves.choose(&mut self.rng).unwrap().clone();
+        SearchResult {
+            pv: vec![mov.clone()],
+            mov,
+            nodes: 0,
+            score: None,
+            mate: None,
+            candidates: vec![],
+        }
+    }
+}
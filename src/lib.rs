@@ -0,0 +1,6 @@
+pub mod arena;
+pub mod chess;
+pub mod engine;
+pub mod mcts;
+pub mod record;
+pub mod selfplay;
@@ -1,7 +1,35 @@
-use crate::chess::{Operation, Status, Turn};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::time::Duration;
 
-mod chess;
-mod mcts;
+use myomyw_chess::arena::{Arena, Game, Score, Sprt, Verdict};
+use myomyw_chess::chess::handicap::{Handicap, Odds, PRESETS};
+use myomyw_chess::chess::tablebase;
+use myomyw_chess::chess::tablebase::{Tablebase, Value};
+use myomyw_chess::chess::{Operation, Rules, Status, TenseRule, Turn};
+use myomyw_chess::engine::background::Background;
+use myomyw_chess::engine::book::{Book, Choice, Opening};
+use myomyw_chess::engine::features::{ParseError, Weights};
+use myomyw_chess::engine::pns::Outcome;
+use myomyw_chess::engine::{book, pns, Control, Engine, Limits, SearchResult};
+use myomyw_chess::mcts::Mcts;
+use myomyw_chess::record::{notation, Record};
+use myomyw_chess::selfplay::Generator;
+
+use myomyw_chess::{chess, engine};
+
+/// How often a background search is checked while the AI thinks.
+const POLL: Duration = Duration::from_millis(20);
+/// Polls between progress lines.
+const SHOW_EVERY: u32 = 50;
+/// Candidates listed by the analysis.
+const TOP: usize = 5;
+/// Positions the proof search may expand when no node limit is set.
+const PROVE_NODES: u64 = 1_000_000;
+/// Deepest opening book built here; each ply can triple the positions searched.
+const MAX_BOOK_PLIES: usize = 8;
+/// Games after which a test that has not decided gives up.
+const MAX_SPRT_GAMES: u32 = 20_000;
 
 fn main() {
     println!("Make Your Opponent Make You WIN! Chess");
@@ -9,75 +37,709 @@ fn main() {
     println!();
     rules();
     println!();
-    let mut ai = None;
+    let mut players: [Option<Box<dyn Engine>>; 2] = [None, None];
+    let mut rules = Rules::default();
+    let mut handicap = None;
+    let mut seed = None;
+    let mut level = None;
+    let mut limits = Limits::default();
+    let mut ponder = false;
+    let mut weights = None;
+    let mut book: Option<Opening> = None;
     loop {
         println!("输入 begin 开始，ai 进入 AI 对战");
-        let mut line = String::new();
-        std::io::stdin().read_line(&mut line).unwrap();
+        println!("pass 开关停着规则（当前：{}）", match rules {
+            Rules { pass: false, .. } => "关",
+            Rules { flip_after_pass: false, double_pass_ends: false, .. } => "开",
+            Rules { flip_after_pass: true, double_pass_ends: false, .. } => "开，停着后翻转",
+            Rules { flip_after_pass: false, double_pass_ends: true, .. } => "开，连停两次结束",
+            Rules { flip_after_pass: true, double_pass_ends: true, .. } => "开，停着后翻转，连停两次结束",
+        });
+        println!("size 设置棋盘大小[{}-{}]（当前：{2}x{2}）", chess::MIN_SIZE, chess::N, rules.size);
+        println!("snake 设置蛇规则（当前：至少{}子{}{}）", rules.min_snake,
+                 if rules.bounded_snakes { "，两端须为边界或空位" } else { "" },
+                 if rules.tense == TenseRule::EndsAndMiddle { "，趋势须两端与中间一致" } else { "" });
+        println!("handicap 设置让子（当前：{}）", handicap.as_ref().map_or(String::from("无"), describe));
+        println!("level 设置 AI 难度[1-{}]（当前：{}）", engine::LEVELS, level.map_or(String::from("默认"), |level: u32| level.to_string()));
+        println!("time 设置 AI 思考时间（当前：{}）", limits.time.map_or(String::from("不限"), |time| format!("{} 秒", time.as_secs_f64())));
+        println!("weights 载入 AI 评估权重文件（当前：{}）", weights.as_ref().map_or("默认", |(file, _): &(String, Weights)| file.as_str()));
+        println!("tablebase 载入残局库（当前：{}）", tablebase::installed().map_or(String::from("无"), |tablebase| format!("{} 个局面", tablebase.positions())));
+        println!("book 载入或生成开局库（当前：{}）", book.as_ref().map_or(String::from("无"), |(book, _)| format!("{} 个局面", book.positions())));
+        println!("arena 引擎对战多局并统计");
+        println!("sprt 引擎对战至序贯检验得出结论");
+        println!("selfplay 生成自对弈训练数据");
+        println!("ponder 开关 AI 在人类回合后台思考（当前：{}）", if ponder { "开" } else { "关" });
+        println!("random 随机开局（当前：{}）", seed.map_or(String::from("无"), |seed: u64| seed.to_string()));
+        let line = read_line();
         match line.to_lowercase().trim() {
             "begin" => {
                 break;
             }
             "ai" => {
-                println!("请选择AI先后手 A 先手 B 后手");
-                let mut line = String::new();
-                std::io::stdin().read_line(&mut line).unwrap();
-                match line.to_lowercase().trim() {
-                    "a" => ai = Some(Turn::A),
-                    "b" => ai = Some(Turn::B),
-                    _ => continue,
-                }
+                players = [read_player(&Turn::A), read_player(&Turn::B)];
                 break;
             }
+            "pass" => {
+                rules.pass = !rules.pass;
+                if rules.pass {
+                    println!("停着后是否翻转？Y 是 N 否");
+                    let line = read_line();
+                    rules.flip_after_pass = line.to_lowercase().trim() == "y";
+                    println!("连续两次停着是否结束对局？Y 是 N 否");
+                    let line = read_line();
+                    rules.double_pass_ends = line.to_lowercase().trim() == "y";
+                }
+            }
+            "size" => {
+                println!("输入棋盘边长[{}-{}]", chess::MIN_SIZE, chess::N);
+                let line = read_line();
+                match line.trim().parse::<usize>() {
+                    Ok(size) if (chess::MIN_SIZE..=chess::N).contains(&size) => {
+                        rules.size = size;
+                        rules.min_snake = rules.min_snake.min(size);
+                        // the layout was drawn and checked for the old board
+                        seed = None;
+                    }
+                    _ => println!("输入错误！"),
+                }
+            }
+            "snake" => read_snake_rules(&mut rules),
+            "handicap" => handicap = read_handicap(),
+            "level" => {
+                println!("输入难度[1-{}]，直接回车使用默认", engine::LEVELS);
+                let line = read_line();
+                level = match line.trim().parse() {
+                    Ok(n) if (1..=engine::LEVELS).contains(&n) => Some(n),
+                    _ => None,
+                };
+                let time = limits.time;
+                limits = level.map_or(Limits::default(), engine::level);
+                limits.time = time;
+            }
+            "time" => {
+                println!("输入 AI 思考时间（秒），直接回车不限");
+                let line = read_line();
+                limits.time = match line.trim().parse::<f64>() {
+                    Ok(secs) if secs > 0.0 => Some(std::time::Duration::from_secs_f64(secs)),
+                    _ => None,
+                };
+            }
+            "weights" => weights = read_weights(),
+            "tablebase" => read_tablebase(&rules),
+            "book" => book = read_book(&rules, limits, loaded(&weights)),
+            "arena" => arena(rules, limits, &book),
+            "sprt" => sprt(rules, limits, &book),
+            "selfplay" => selfplay(rules, limits, loaded(&weights)),
+            "ponder" => ponder = !ponder,
+            "random" => {
+                println!("输入种子，直接回车随机生成公平开局");
+                let line = read_line();
+                seed = match line.trim() {
+                    "" => Some(chess::layout::fair_seed(rules)),
+                    line => match line.parse() {
+                        Ok(seed) => Some(seed),
+                        Err(_) => {
+                            println!("输入错误！");
+                            None
+                        }
+                    },
+                };
+            }
             _ => {}
         }
     }
-    let ai = ai;
-    let mut board = chess::Chessboard::new();
+    let weights = loaded(&weights);
+    players.iter_mut().flatten().for_each(|engine| {
+        engine.set_weights(weights);
+        engine.set_book(book.clone());
+    });
+    let mut board = start(rules, &handicap, seed);
+    let mut record = Record::new(rules, handicap, seed);
     loop {
         println!();
         println!("{}", board);
         match board.check() {
             Status::Free(turn) => {
                 println!("轮到 {} 走棋", turn);
-                let (r, c, op) = if matches!((ai.clone(), board.check()), (Some(turn1), Status::Free(turn2)) if turn1 == turn2) {
-                    println!("AI 计算中");
-                    mcts::search(&board, &ai.clone().unwrap())
-                } else {
-                    read_loc()
-                };
-                board = match board.next(r, c, op) {
-                    Ok(board) => board,
-                    Err(chess::Errors::OutOfBound) => {
-                        println!("棋子出界！");
-                        continue;
+                if let Some(value) = tablebase::probe(&board) {
+                    println!("残局库：{}", show_value(&turn, value));
+                }
+                let (r, c, op) = match players[side(&turn)].take() {
+                    Some(engine) => {
+                        let (engine, result) = think(engine, &board, limits);
+                        players[side(&turn)] = Some(engine);
+                        match result.score {
+                            Some(score) => println!("{} 走 {}（{} 节点，评分 {}）", turn, notation(&result.mov), result.nodes, score),
+                            None => println!("{} 走 {}", turn, notation(&result.mov)),
+                        }
+                        if result.pv.len() > 1 {
+                            println!("预期变化 {}", show_pv(&result.pv));
+                        }
+                        if let Some(mate) = result.mate {
+                            println!("{} {}", turn, show_mate(mate));
+                        }
+                        result.mov
                     }
-                    Err(chess::Errors::WrongTurn) => {
-                        println!("请选择己方棋子！");
-                        continue;
+                    None if board.sits_out() => chess::PASS,
+                    None => {
+                        // the opponent searches the replies while the human thinks
+                        let other = 1 - side(&turn);
+                        let pondering = if ponder {
+                            players[other].take().map(|engine| Background::spawn(engine, board.clone(), limits))
+                        } else {
+                            None
+                        };
+                        let mov = read_loc(&board, limits, weights);
+                        if let Some(search) = pondering {
+                            players[other] = Some(search.stop().0);
+                        }
+                        mov
                     }
-                    Err(chess::Errors::Stuck) => {
-                        println!("目标有子！");
+                };
+                if matches!(op, Operation::Pass) {
+                    println!("{} 停着", turn);
+                }
+                board = match board.next(r, c, op.clone()) {
+                    Ok(board) => board,
+                    Err(err) => {
+                        println!("{}", explain(&err));
                         continue;
                     }
                 };
+                record.push((r, c, op));
             }
             Status::Win(turn) => {
                 println!("{} 获胜", turn);
+                save(&record);
                 return;
             }
+            Status::Draw => {
+                println!("和棋");
+                save(&record);
+                return;
+            }
+        }
+    }
+}
+
+/// The first position of a game with these settings.
+fn start(rules: Rules, handicap: &Option<Handicap>, seed: Option<u64>) -> chess::Chessboard {
+    let board = match seed {
+        Some(seed) => chess::Chessboard::from_seed(rules, seed),
+        None => chess::Chessboard::start(rules),
+    };
+    match handicap {
+        Some(handicap) => board.with_handicap(handicap),
+        None => board,
+    }
+}
+
+/// Lines from stdin, read on their own thread so that a thinking AI can be interrupted.
+fn input() -> &'static Mutex<Receiver<String>> {
+    static INPUT: OnceLock<Mutex<Receiver<String>>> = OnceLock::new();
+    INPUT.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lines() {
+                if sender.send(line.unwrap()).is_err() {
+                    break;
+                }
+            }
+        });
+        Mutex::new(receiver)
+    })
+}
+
+fn read_line() -> String {
+    match input().lock().unwrap().recv() {
+        Ok(line) => line,
+        Err(_) => std::process::exit(0),
+    }
+}
+
+/// Searches in the background, showing the best move so far every second, until
+/// the search ends or enter is pressed.
+fn think(engine: Box<dyn Engine>, board: &chess::Chessboard, limits: Limits) -> (Box<dyn Engine>, SearchResult) {
+    println!("AI（{}）计算中，按回车立即出着", engine.name());
+    watch(Background::spawn(engine, board.clone(), limits), |progress| {
+        println!("当前最佳 {}（{} 节点）", notation(&progress.mov), progress.nodes);
+    })
+}
+
+/// Lists the moves MCTS likes best for the side to move, refreshed every second
+/// until the search ends or enter is pressed.
+fn analyze(board: &chess::Chessboard, limits: Limits, weights: Weights) {
+    println!("分析中，按回车结束");
+    let mut engine = Mcts::new();
+    engine.set_weights(weights);
+    let (_, result) = watch(Background::spawn(Box::new(engine), board.clone(), limits), show_candidates);
+    show_candidates(&result);
+    println!("预期变化：");
+    show_line(board, &result.pv);
+}
+
+/// Looks for a forced win of the side to move with a proof-number search.
+fn prove(board: &chess::Chessboard, limits: Limits) {
+    println!("证明中……");
+    let nodes = limits.nodes.unwrap_or(PROVE_NODES);
+    match pns::solve(board, nodes, &Control::default()) {
+        (Outcome::Win(line), nodes) => {
+            println!("{} 必胜，{} 步内结束（{} 节点）：", board.turn(), line.len(), nodes);
+            show_line(board, &line);
+        }
+        (Outcome::NoWin, nodes) => println!("{} 无法在 {} 步内强制获胜（{} 节点）", board.turn(), pns::MAX_PLIES, nodes),
+        (Outcome::Unknown, nodes) => println!("{} 节点内未能证明", nodes),
+    }
+}
+
+/// Each move of `line` with the board after it.
+fn show_line(board: &chess::Chessboard, line: &[(i32, i32, Operation)]) {
+    let mut turn = board.turn().clone();
+    for (mov, next) in engine::line(board, line) {
+        println!("{} 走 {}", turn, notation(&mov));
+        println!("{}", next);
+        turn = next.turn().clone();
+    }
+}
+
+fn show_candidates(result: &SearchResult) {
+    println!("{} 节点：", result.nodes);
+    for (i, candidate) in result.candidates.iter().take(TOP).enumerate() {
+        println!("{}. {}  访问 {}  均分 {}  先验 {:.2}  变化 {}{}", i + 1, notation(&candidate.mov),
+                 candidate.visits, candidate.mean, candidate.prior, show_pv(&candidate.pv),
+                 candidate.mate.map_or(String::new(), |mate| format!("  {}", show_mate(mate))));
+    }
+}
+
+/// The mover's forced result, from `SearchResult::mate`.
+fn show_mate(mate: i64) -> String {
+    if mate > 0 {
+        format!("必胜，{} 步内结束", mate)
+    } else {
+        format!("必败，{} 步内结束", -mate)
+    }
+}
+
+fn show_pv(pv: &[(i32, i32, Operation)]) -> String {
+    pv.iter().map(notation).collect::<Vec<_>>().join(", ")
+}
+
+/// Waits for a background search, showing its progress every second, until it ends
+/// or enter is pressed.
+fn watch(search: Background, show: impl Fn(&SearchResult)) -> (Box<dyn Engine>, SearchResult) {
+    let mut polls = 0;
+    loop {
+        match input().lock().unwrap().recv_timeout(POLL) {
+            Ok(_) => return search.stop(),
+            Err(RecvTimeoutError::Disconnected) => return search.wait(),
+            Err(RecvTimeoutError::Timeout) if search.is_finished() => return search.wait(),
+            Err(RecvTimeoutError::Timeout) => {
+                polls += 1;
+                if polls % SHOW_EVERY == 0 {
+                    if let Some(progress) = search.progress() {
+                        show(&progress);
+                    }
+                }
+            }
         }
     }
 }
 
+fn side(turn: &Turn) -> usize {
+    match turn {
+        Turn::A => 0,
+        Turn::B => 1,
+    }
+}
+
+/// Plays a match between two engines and reports the results from the first
+/// engine's side.
+fn arena(rules: Rules, limits: Limits, book: &Option<Opening>) {
+    let mut arena = read_arena(rules, limits, book);
+    println!("输入对局数，每个布局双方各执先一次");
+    let games: u32 = match read_line().trim().parse() {
+        Ok(games) if games > 0 => games,
+        _ => {
+            println!("输入错误！");
+            return;
+        }
+    };
+    for _ in 0..games {
+        let game = arena.play();
+        show_game(&arena, &game);
+    }
+    show_stats(&arena);
+}
+
+/// Plays games until the test decides or `MAX_SPRT_GAMES` are played, then
+/// offers to save a report.
+fn sprt(rules: Rules, limits: Limits, book: &Option<Opening>) {
+    let mut arena = read_arena(rules, limits, book);
+    let default = Sprt::default();
+    println!("输入 Elo 下界 上界 alpha beta，直接回车使用 {} {} {} {}", default.elo0, default.elo1, default.alpha, default.beta);
+    let line = read_line();
+    let sprt = match line.split_whitespace().map(str::parse).collect::<Result<Vec<f64>, _>>().as_deref() {
+        Ok([]) => default,
+        Ok(&[elo0, elo1, alpha, beta]) if elo0 < elo1 && 0.0 < alpha && alpha < 0.5 && 0.0 < beta && beta < 0.5 => {
+            Sprt { elo0, elo1, alpha, beta }
+        }
+        _ => {
+            println!("输入错误！");
+            return;
+        }
+    };
+    let (lower, upper) = sprt.bounds();
+    while arena.stats().games() < MAX_SPRT_GAMES && sprt.verdict(arena.stats()).is_none() {
+        let game = arena.play();
+        show_game(&arena, &game);
+        println!("LLR {:.3}（{:.3} 至 {:.3}）", sprt.llr(arena.stats()), lower, upper);
+    }
+    show_stats(&arena);
+    match sprt.verdict(arena.stats()) {
+        Some(Verdict::Better) => println!("检验通过：{} 更强", arena.names()[0]),
+        Some(Verdict::Worse) => println!("检验未通过：{} 并不更强", arena.names()[0]),
+        None => println!("{} 局内未能判定", MAX_SPRT_GAMES),
+    }
+    println!("输入文件名保存报告，直接回车跳过");
+    let file = read_line().trim().to_string();
+    if !file.is_empty() && std::fs::write(&file, sprt.report(arena.names(), arena.stats())).is_err() {
+        println!("保存失败！");
+    }
+}
+
+/// Writes self-play shards up to the number asked for, continuing after the
+/// shards already written under the same name.
+fn selfplay(rules: Rules, limits: Limits, weights: Weights) {
+    let mut engine = read_engine("自对弈");
+    engine.set_weights(weights);
+    println!("输入数据文件名前缀");
+    let prefix = read_line().trim().to_string();
+    if prefix.is_empty() {
+        return;
+    }
+    println!("输入分片数和每个分片的对局数");
+    let numbers: Vec<u32> = read_line().split_whitespace().filter_map(|number| number.parse().ok()).collect();
+    let (shards, games) = match numbers[..] {
+        [shards, games] if shards > 0 && games > 0 => (shards, games),
+        _ => {
+            println!("输入错误！");
+            return;
+        }
+    };
+    let mut generator = Generator::new(engine, rules, limits, prefix.into(), games);
+    let first = generator.next_shard();
+    if first > 0 {
+        println!("已有 {} 个分片，从第 {} 个继续", first, first + 1);
+    }
+    for shard in first..shards {
+        let mut game = 0;
+        let written = generator.write_shard(shard, |plies| {
+            game += 1;
+            println!("分片 {} 第 {} 局：{} 步", shard + 1, game, plies);
+        });
+        match written {
+            Ok(positions) => println!("已写入 {}（{} 个局面）", generator.path(shard).display(), positions),
+            Err(_) => {
+                println!("保存失败！");
+                return;
+            }
+        }
+    }
+}
+
+/// Asks for two engines, the weights of each and, if a book is loaded, whether
+/// each plays from it.
+fn read_arena(rules: Rules, limits: Limits, book: &Option<Opening>) -> Arena {
+    let mut engines = [read_engine("第一个"), read_engine("第二个")];
+    for engine in &mut engines {
+        println!("{} 的权重：", engine.name());
+        if let Some((_, weights)) = read_weights() {
+            engine.set_weights(weights);
+        }
+        if book.is_some() {
+            println!("{} 是否使用开局库？Y 是 N 否", engine.name());
+            if read_line().to_lowercase().trim() == "y" {
+                engine.set_book(book.clone());
+            }
+        }
+    }
+    Arena::new(engines, rules, limits)
+}
+
+fn show_game(arena: &Arena, game: &Game) {
+    println!("第 {} 局（布局 {}）：{} 执 {}，{}，{} 步", arena.stats().games(), game.seed, arena.names()[0], game.first, match game.score {
+        Score::Win => "胜",
+        Score::Loss => "负",
+        Score::Draw => "和",
+    }, game.plies);
+}
+
+fn show_stats(arena: &Arena) {
+    let stats = arena.stats();
+    let [first, second] = arena.names();
+    println!("{} 对 {}：胜 {} 负 {} 和 {}，得分率 {:.1}%，平均 {:.1} 步", first, second,
+             stats.wins, stats.losses, stats.draws, stats.score() * 100.0, stats.average_plies());
+    let (elo, low, high) = stats.elo();
+    println!("Elo 差 {:+.1}（95% 置信区间 {:+.1} 至 {:+.1}）", elo, low, high);
+}
+
+fn read_engine(which: &str) -> Box<dyn Engine> {
+    loop {
+        println!("请选择{}引擎：{}", which, engine::NAMES.join(" "));
+        match engine::by_name(read_line().to_lowercase().trim()) {
+            Some(engine) => return engine,
+            None => println!("输入错误！"),
+        }
+    }
+}
+
+fn read_player(turn: &Turn) -> Option<Box<dyn Engine>> {
+    loop {
+        println!("请选择 {} 方：human 人类，或 AI 引擎 {}", turn, engine::NAMES.join(" "));
+        let line = read_line();
+        match line.to_lowercase().trim() {
+            "human" => return None,
+            name => match engine::by_name(name) {
+                Some(engine) => return Some(engine),
+                None => println!("输入错误！"),
+            },
+        }
+    }
+}
+
+/// Loads a tablebase file written by the `solve` program.
+fn read_tablebase(rules: &Rules) {
+    if tablebase::installed().is_some() {
+        println!("残局库已载入！");
+        return;
+    }
+    println!("输入残局库文件名（由 solve 程序生成）");
+    let file = read_line().trim().to_string();
+    if file.is_empty() {
+        return;
+    }
+    let tablebase = match Tablebase::load(&file) {
+        Ok(tablebase) => tablebase,
+        Err(_) => {
+            println!("读取失败！");
+            return;
+        }
+    };
+    if tablebase.rules() != rules {
+        println!("注意：残局库的规则与当前规则不同，只在规则相同时使用");
+    }
+    println!("已载入 {} 个局面", tablebase.positions());
+    tablebase::install(tablebase);
+}
+
+/// Loads an opening book, or builds and saves one for `rules` with MCTS at
+/// `limits` and `weights` if the file does not exist yet; no file name or a
+/// failure leaves no book.
+fn read_book(rules: &Rules, limits: Limits, weights: Weights) -> Option<Opening> {
+    println!("输入开局库文件名，直接回车不用开局库");
+    let file = read_line().trim().to_string();
+    if file.is_empty() {
+        return None;
+    }
+    let book = if std::path::Path::new(&file).exists() {
+        let text = match std::fs::read_to_string(&file) {
+            Ok(text) => text,
+            Err(_) => {
+                println!("读取失败！");
+                return None;
+            }
+        };
+        match Book::parse(&text) {
+            Ok(book) => book,
+            Err(book::ParseError::BadHeader) => {
+                println!("不是开局库文件！");
+                return None;
+            }
+            Err(book::ParseError::UnsupportedVersion(version)) => {
+                println!("不支持第 {} 版开局库！", version);
+                return None;
+            }
+            Err(book::ParseError::BadLine(line)) => {
+                println!("第 {} 行格式错误！", line);
+                return None;
+            }
+        }
+    } else {
+        println!("文件不存在，将按当前规则和难度生成。输入开局库步数[1-{}]", MAX_BOOK_PLIES);
+        let plies = match read_line().trim().parse() {
+            Ok(plies) if (1..=MAX_BOOK_PLIES).contains(&plies) => plies,
+            _ => {
+                println!("输入错误！");
+                return None;
+            }
+        };
+        let mut engine = Mcts::new();
+        engine.set_weights(weights);
+        let book = Book::build(*rules, plies, &mut engine, limits, |positions| println!("已搜索 {} 个局面", positions));
+        if std::fs::write(&file, book.to_string()).is_err() {
+            println!("保存失败！");
+        }
+        book
+    };
+    if book.rules() != rules {
+        println!("注意：开局库的规则与当前规则不同，只在规则相同时使用");
+    }
+    println!("已载入 {} 个局面", book.positions());
+    println!("是否按权重随机选择开局？Y 是 N 否");
+    let choice = if read_line().to_lowercase().trim() == "y" { Choice::Weighted } else { Choice::Best };
+    Some((Arc::new(book), choice))
+}
+
+/// A tablebase value for `turn`, the side to move.
+fn show_value(turn: &Turn, value: Value) -> String {
+    match value {
+        Value::Win(plies) => format!("{} {}", turn, show_mate(plies as i64)),
+        Value::Loss(plies) => format!("{} {}", turn, show_mate(-(plies as i64))),
+        Value::Draw => String::from("和棋"),
+    }
+}
+
+/// The weights read by `read_weights`, or the default ones.
+fn loaded(weights: &Option<(String, Weights)>) -> Weights {
+    weights.as_ref().map_or_else(Weights::default, |(_, weights)| *weights)
+}
+
+/// Asks for a weights file; `None` goes back to the default weights.
+fn read_weights() -> Option<(String, Weights)> {
+    println!("输入权重文件名，直接回车使用默认权重");
+    let file = read_line().trim().to_string();
+    if file.is_empty() {
+        return None;
+    }
+    let text = match std::fs::read_to_string(&file) {
+        Ok(text) => text,
+        Err(_) => {
+            println!("读取失败！");
+            return None;
+        }
+    };
+    match Weights::parse(&text) {
+        Ok(weights) => {
+            println!("已载入权重：");
+            print!("{}", weights);
+            Some((file, weights))
+        }
+        Err(ParseError::UnknownFeature(line, name)) => {
+            println!("第 {} 行：未知特征 {}！", line, name);
+            None
+        }
+        Err(ParseError::BadWeight(line)) => {
+            println!("第 {} 行：应为特征名和权重！", line);
+            None
+        }
+    }
+}
+
+fn explain(err: &chess::Errors) -> String {
+    match *err {
+        chess::Errors::OriginOutOfBound(r, c) => format!("起点 ({}, {}) 不在棋盘上！", r + 1, c + 1),
+        chess::Errors::DestinationOutOfBound(r, c) => format!("棋子出界！目标 ({}, {}) 不在棋盘上", r + 1, c + 1),
+        chess::Errors::EmptyOrigin(r, c) => format!("({}, {}) 没有棋子！", r + 1, c + 1),
+        chess::Errors::OpponentStone(r, c) => format!("({}, {}) 是对方棋子，请选择己方棋子！", r + 1, c + 1),
+        chess::Errors::Occupied(r, c) => format!("目标 ({}, {}) 有子！", r + 1, c + 1),
+        chess::Errors::GameOver => String::from("对局已结束！"),
+        chess::Errors::PassForbidden => String::from("不允许停着！"),
+        chess::Errors::MustPass => String::from("让步期间本回合须停着！"),
+    }
+}
+
+fn save(record: &Record) {
+    println!();
+    println!("棋谱：");
+    print!("{}", record);
+    println!("输入文件名保存棋谱，直接回车跳过");
+    let line = read_line();
+    let path = line.trim();
+    if path.is_empty() {
+        return;
+    }
+    if std::fs::write(path, record.to_string()).is_err() {
+        println!("保存失败！");
+    }
+}
+
+fn read_snake_rules(rules: &mut Rules) {
+    loop {
+        println!("输入蛇的最短长度[2-{}]", rules.size);
+        let line = read_line();
+        match line.trim().parse::<usize>() {
+            Ok(len) if (2..=rules.size).contains(&len) => {
+                rules.min_snake = len;
+                break;
+            }
+            _ => println!("输入错误！"),
+        }
+    }
+    println!("蛇的两端是否须为边界或空位？Y 是 N 否");
+    let line = read_line();
+    rules.bounded_snakes = line.to_lowercase().trim() == "y";
+    println!("蛇的趋势是否须两端与中间棋子一致？（蛇黑白交替，故只有5子、9子等长度的蛇满足）Y 是 N 否");
+    let line = read_line();
+    rules.tense = if line.to_lowercase().trim() == "y" { TenseRule::EndsAndMiddle } else { TenseRule::Ends };
+}
+
+fn read_handicap() -> Option<Handicap> {
+    println!("请选择让子方（较强的一方）A 或 B，直接回车取消让子");
+    let line = read_line();
+    let stronger = match line.to_lowercase().trim() {
+        "a" => Turn::A,
+        "b" => Turn::B,
+        _ => return None,
+    };
+    loop {
+        println!("请选择让子方式");
+        for (i, odds) in PRESETS.iter().enumerate() {
+            println!("{} {}", i + 1, describe(&Handicap { stronger: stronger.clone(), odds: *odds }));
+        }
+        let line = read_line();
+        match line.trim().parse::<usize>() {
+            Ok(i) if (1..=PRESETS.len()).contains(&i) => {
+                return Some(Handicap { stronger, odds: PRESETS[i - 1] });
+            }
+            _ => println!("输入错误！"),
+        }
+    }
+}
+
+fn describe(handicap: &Handicap) -> String {
+    let weaker = match handicap.stronger {
+        Turn::A => Turn::B,
+        Turn::B => Turn::A,
+    };
+    match handicap.odds {
+        Odds::Stones(n) => format!("{} 让{}子（{} 少{}子开局）", handicap.stronger, n, weaker, n),
+        Odds::Moves(n) => format!("{} 让{}步（{} 先走，{} 停走{}次）", handicap.stronger, n, weaker, handicap.stronger, n),
+    }
+}
+
 /// (r, c, op)
-fn read_loc() -> (i32, i32, Operation) {
+fn read_loc(board: &chess::Chessboard, limits: Limits, weights: Weights) -> (i32, i32, Operation) {
+    let pass = board.rules().pass;
     let location;
     let operation;
     loop {
-        println!("输入坐标 行[1-{0}] 列[1-{0}]", chess::N);
-        let mut line = String::new();
-        std::io::stdin().read_line(&mut line).unwrap();
+        if pass {
+            println!("输入坐标 行[1-{0}] 列[1-{0}]，或输入 pass 停着，analyze 分析局面，solve 证明必胜", board.size());
+        } else {
+            println!("输入坐标 行[1-{0}] 列[1-{0}]，或输入 analyze 分析局面，solve 证明必胜", board.size());
+        }
+        let line = read_line();
+        if pass && line.to_lowercase().trim() == "pass" {
+            return chess::PASS;
+        }
+        if line.to_lowercase().trim() == "analyze" {
+            analyze(board, limits, weights);
+            continue;
+        }
+        if line.to_lowercase().trim() == "solve" {
+            prove(board, limits);
+            continue;
+        }
         let subs: Vec<_> = line.trim().split_ascii_whitespace().map(String::from).collect();
         if subs.len() != 2 {
             println!("输入错误！");
@@ -93,7 +755,7 @@ fn read_loc() -> (i32, i32, Operation) {
                 }
             };
         }
-        if loc[0] < 1 || loc[0] > chess::N as i32 || loc[1] < 1 || loc[1] > chess::N as i32 {
+        if !board.on_board(loc[0] - 1, loc[1] - 1) {
             println!("输入错误！");
             continue;
         }
@@ -102,8 +764,7 @@ fn read_loc() -> (i32, i32, Operation) {
     }
     loop {
         println!("输入 U 向上 D 向下 L 向左 R 向右");
-        let mut line = String::new();
-        std::io::stdin().read_line(&mut line).unwrap();
+        let line = read_line();
         match line.to_lowercase().trim() {
             "u" => operation = Operation::Up,
             "d" => operation = Operation::Down,
@@ -121,9 +782,9 @@ fn read_loc() -> (i32, i32, Operation) {
 
 fn rules() {
     println!("规则：");
-    println!("1、棋盘{0}x{0}，开局时双方有5个棋子，分别在左上和右下角", chess::N);
+    println!("1、棋盘{0}x{0}（可改为{1}x{1}至{0}x{0}），开局时双方棋子沿边排成L形，分别在左上和右下角，{0}x{0}时各5个", chess::N, chess::MIN_SIZE);
     println!("2、双方交替移动棋子，一次只能沿横竖方向移动一格");
-    println!("3、胜负条件是【让对方翻转掉自己的所有棋子】");
+    println!("3、胜负条件是【让对方翻转掉自己的所有棋子】；轮到的一方若无子可动又不能停着，则为和棋");
     println!("4、在一个方向上一串交替的棋子被称为蛇（snake），如：ABA、ABABA");
     println!("如，ABAAB中ABA构成蛇");
     println!("5、若蛇中某颗棋子在另外一个方向也构成了蛇，那么就把这些交叉的蛇成为叉（cross），如：");
@@ -151,4 +812,6 @@ fn rules() {
     println!(".A.      .A.       .A.");
     println!("BAB  ->  BBB  -\\>  BAB");
     println!(".A.      .A.       .A.");
+    println!("9、（可选）开启停着规则后，可以选择不移动棋子（pass），连续两次停着则对局结束，棋子少的一方获胜");
+    println!("10、（可选）可以设置蛇的最短长度、要求蛇两端为边界或空位、要求趋势由两端与中间棋子共同决定");
 }
\ No newline at end of file
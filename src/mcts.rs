@@ -1,121 +1,702 @@
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
-use mcts::{CycleBehaviour, Evaluator, GameState, MCTS, MCTSManager, MoveEvaluation, MoveList, Player, SearchHandle};
-use mcts::transposition_table::{ApproxTable, TranspositionHash};
-use mcts::tree_policy::UCTPolicy;
+use rand::seq::{IndexedRandom, SliceRandom};
 
-use crate::chess::{Chessboard, Operation, Status, Turn};
+use crate::chess::rules::{count_cross, count_snake, Tense};
+use crate::chess::tablebase;
+use crate::chess::tablebase::Value;
+use crate::chess::{Chess, Chessboard, Operation, Status, Turn, N};
+use crate::engine::features::Weights;
+use crate::engine::book::Opening;
+use crate::engine::pns;
+use crate::engine::pns::Outcome;
+use crate::engine::{evaluate, Candidate, Control, Engine, Limits, SearchResult};
+
+/// Playouts when no limit is given.
+const PLAYOUTS: u32 = 1e6 as u32;
+/// How often a running search reports its best move.
+const REPORT_EVERY: Duration = Duration::from_millis(100);
+/// Weight of the prior-driven exploration bonus against values from -1 to 1.
+const EXPLORATION: f64 = 1.5;
+/// Prior weight of a move that flips some of the mover's stones to the opponent,
+/// against 1 for a quiet move.
+const SHED: f64 = 4.0;
+/// Prior weight of a move that flips opponent stones to the mover.
+const TAKE: f64 = 0.25;
+/// Prior factor for a move that leaves the moved stone without an own neighbour.
+const ISOLATED: f64 = 0.5;
+/// Prior factor for a move whose flips leave a tense snake behind that will hand
+/// opponent stones back to the mover on the opponent's move.
+const OPENS: f64 = 0.5;
+/// Playouts stop descending after this many plies, since positions can repeat.
+const MAX_DEPTH: usize = 256;
+/// How many plies past the last root the next position is looked for.
+const REUSE_DEPTH: usize = 4;
+/// Positions the proof-number search may expand before the tree search starts.
+const INSTANT_WIN: u64 = 2000;
+
+/// Leaf values run from `-SCALE`, best for B, to `SCALE`, best for A.
+const SCALE: f64 = 1000.0;
+/// Score of a win reached at the root, above any leaf value; each ply deeper
+/// costs one point.
+const WIN: i64 = 2 * SCALE as i64;
+/// Rollouts still running after this many plies are scored by their features.
+const ROLLOUT_PLIES: usize = 4 * N * N;
+
+/// How a position new to the tree is scored.
+#[derive(Clone, Copy, Debug)]
+pub enum Leaf {
+    /// By the share of the stones each side has left.
+    Static,
+    /// By the end of a game played out from it.
+    Rollout(Policy),
+    /// By both, the rollout having this weight.
+    Blend(Policy, f64),
+}
+
+/// How a rollout picks its moves.
+#[derive(Clone, Copy, Debug)]
+pub enum Policy {
+    Random,
+    /// The move with the best static score, ties broken at random.
+    Greedy,
+}
 
 #[derive(Clone)]
-struct ChessGame {
-    board: Chessboard,
+enum StateEval {
+    /// The winner and the ply it won at, counted like `Mcts::ply`.
+    Win(Turn, u32),
+    Draw,
+    /// A leaf value, see `SCALE`.
+    Eval(i32),
+}
+
+/// A game-theoretic value found by the search.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Proof {
+    /// The side wins at the latest this many plies from the node.
+    Win(Turn, u32),
+    Draw,
+}
+
+struct Node {
+    /// The side to move, or `None` once the game is over.
+    player: Option<Turn>,
+    ply: u32,
+    evaln: StateEval,
+    edges: Vec<Edge>,
+    proof: OnceLock<Proof>,
+}
+
+struct Edge {
+    mov: (i32, i32, Operation),
+    prior: f64,
+    visits: AtomicU64,
+    sum_rewards: AtomicI64,
+    child: OnceLock<Box<Node>>,
+}
+
+impl Node {
+    fn new(board: &Chessboard, ply: u32, leaf: Leaf, weights: &Weights) -> Node {
+        let proof = OnceLock::new();
+        let (player, evaln) = match board.check() {
+            Status::Win(who) => {
+                let _ = proof.set(Proof::Win(who.clone(), 0));
+                (None, StateEval::Win(who, ply))
+            }
+            Status::Draw => {
+                let _ = proof.set(Proof::Draw);
+                (None, StateEval::Draw)
+            }
+            Status::Free(turn) => {
+                // the tablebase settles the position, but the node stays open so
+                // that its moves can still be shown
+                let known = tablebase::probe(board).map(|value| match value {
+                    Value::Win(plies) => Proof::Win(turn.clone(), plies),
+                    Value::Loss(plies) => Proof::Win(if turn == Turn::A { Turn::B } else { Turn::A }, plies),
+                    Value::Draw => Proof::Draw,
+                });
+                let evaln = match &known {
+                    Some(Proof::Win(who, plies)) => StateEval::Win(who.clone(), ply + plies),
+                    Some(Proof::Draw) => StateEval::Draw,
+                    None => StateEval::Eval((value(board, leaf, weights) * SCALE) as i32),
+                };
+                if let Some(known) = known {
+                    let _ = proof.set(known);
+                }
+                (Some(turn), evaln)
+            }
+        };
+        let edges = match player {
+            Some(_) => {
+                let moves = board.available();
+                let priors = priors(board, &moves);
+                moves.into_iter().zip(priors).map(|(mov, prior)| Edge::new(mov, prior)).collect()
+            }
+            None => vec![],
+        };
+        Node { player, ply, evaln, edges, proof }
+    }
+
+    /// PUCT: the mean score as a share of a win, plus a bonus that follows
+    /// the prior and shrinks as the move is visited; proven moves count as won,
+    /// drawn or lost outright.
+    fn select(&self, player: &Turn) -> &Edge {
+        let total_visits: u64 = self.edges.iter().map(|edge| edge.visits()).sum();
+        let sqrt_total = ((total_visits + 1) as f64).sqrt();
+        let puct = |edge: &Edge| {
+            let value = match edge.proof() {
+                Some(proof) => rank(proof, player).signum() as f64,
+                None => edge.mean() as f64 / WIN as f64,
+            };
+            value + EXPLORATION * edge.prior * sqrt_total / (edge.visits() + 1) as f64
+        };
+        self.edges.iter().max_by(|a, b| puct(a).total_cmp(&puct(b))).unwrap()
+    }
+
+    /// What the search knows for sure, as seen from the ply the tree counts from.
+    fn proven(&self) -> Option<StateEval> {
+        match self.proof.get()? {
+            Proof::Win(who, plies) => Some(StateEval::Win(who.clone(), self.ply + plies)),
+            Proof::Draw => Some(StateEval::Draw),
+        }
+    }
+
+    /// Proves the node won once a move wins for the side to move, or decided once
+    /// every move is; returns whether it is proven.
+    fn prove(&self) -> bool {
+        if self.proof.get().is_some() {
+            return true;
+        }
+        let Some(player) = &self.player else {
+            return false;
+        };
+        let mut best: Option<&Proof> = None;
+        let mut all = true;
+        for edge in &self.edges {
+            match edge.proof() {
+                Some(proof) if best.is_none_or(|best| rank(proof, player) > rank(best, player)) => best = Some(proof),
+                Some(_) => (),
+                None => all = false,
+            }
+        }
+        let proof = match best {
+            Some(Proof::Win(who, plies)) if who == player || all => Proof::Win(who.clone(), plies + 1),
+            Some(Proof::Draw) if all => Proof::Draw,
+            _ => return false,
+        };
+        let _ = self.proof.set(proof);
+        true
+    }
 }
 
-impl ChessGame {
-    fn from(board: Chessboard) -> ChessGame {
-        ChessGame { board }
+/// How good a proven result is for `player`: quicker wins and slower losses first.
+fn rank(proof: &Proof, player: &Turn) -> i64 {
+    match proof {
+        Proof::Win(who, plies) if who == player => WIN - *plies as i64,
+        Proof::Win(_, plies) => -(WIN - *plies as i64),
+        Proof::Draw => 0,
     }
 }
 
-impl GameState for ChessGame {
-    type Move = (i32, i32, Operation);
-    type Player = Option<Turn>;
-    type MoveList = Vec<(i32, i32, Operation)>;
+/// Plies to a forced end for `player`: positive if it wins, negative if it loses.
+fn mate(proof: &Proof, player: &Turn) -> Option<i64> {
+    match proof {
+        Proof::Win(who, plies) if who == player => Some(*plies as i64 + 1),
+        Proof::Win(_, plies) => Some(-(*plies as i64 + 1)),
+        Proof::Draw => None,
+    }
+}
 
-    fn current_player(&self) -> Self::Player {
-        match self.board.check() {
-            Status::Win(_) => None,
-            Status::Free(turn) => Some(turn),
+impl Edge {
+    fn new(mov: (i32, i32, Operation), prior: f64) -> Edge {
+        Edge {
+            mov,
+            prior,
+            visits: AtomicU64::new(0),
+            sum_rewards: AtomicI64::new(0),
+            child: OnceLock::new(),
         }
     }
 
-    fn available_moves(&self) -> Self::MoveList {
-        match self.current_player() {
-            Some(_) => self.board.available(),
-            None => vec![],
+    fn visits(&self) -> u64 {
+        self.visits.load(Ordering::Relaxed)
+    }
+
+    fn sum_rewards(&self) -> i64 {
+        self.sum_rewards.load(Ordering::Relaxed)
+    }
+
+    fn proof(&self) -> Option<&Proof> {
+        self.child.get()?.proof.get()
+    }
+
+    fn mean(&self) -> i64 {
+        match self.visits() {
+            0 => 0,
+            visits => self.sum_rewards() / visits as i64,
         }
     }
 
-    fn make_move(&mut self, mov: &Self::Move) {
-        self.board = self.board.next(mov.0, mov.1, mov.2.clone()).unwrap();
+    /// The most visited line starting with this move.
+    fn pv(&self) -> Vec<(i32, i32, Operation)> {
+        let mut line = vec![self.mov.clone()];
+        let mut node = self.child.get();
+        while let Some(best) = node.and_then(|node| node.edges.iter().filter(|edge| edge.visits() > 0).max_by_key(|edge| edge.visits())) {
+            line.push(best.mov.clone());
+            node = best.child.get();
+        }
+        line
     }
 }
 
-impl TranspositionHash for ChessGame {
-    fn hash(&self) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        self.board.hash(&mut hasher);
-        hasher.finish()
+/// UCT search that keeps the part of its tree still reachable from the next position.
+pub struct Mcts {
+    leaf: Leaf,
+    weights: Weights,
+    book: Option<Opening>,
+    /// The tree of the last search, with the position at its root.
+    tree: Option<(Chessboard, Box<Node>)>,
+    /// Plies between the first root and the current one.
+    ply: u32,
+}
+
+impl Default for Mcts {
+    fn default() -> Self {
+        Mcts::new()
     }
 }
 
-#[derive(Clone)]
-enum StateEval {
-    Win(Turn),
-    Eval(i32),
+impl Mcts {
+    pub fn new() -> Mcts {
+        Mcts { leaf: Leaf::Static, weights: Weights::default(), book: None, tree: None, ply: 0 }
+    }
+
+    pub fn with_leaf(self, leaf: Leaf) -> Mcts {
+        Mcts { leaf, ..self }
+    }
+
+    /// Takes the subtree for `board` out of the last tree, if it was reached.
+    fn reuse(&mut self, board: &Chessboard) -> Option<Box<Node>> {
+        let (last, mut root) = self.tree.take()?;
+        let path = find(&root, &last, board, REUSE_DEPTH)?;
+        for &i in &path {
+            root = root.edges[i].child.take()?;
+        }
+        self.ply += path.len() as u32;
+        Some(root)
+    }
 }
 
-struct ChessEvaluator {
-    computer: Turn,
+impl Engine for Mcts {
+    fn name(&self) -> &'static str {
+        match self.leaf {
+            Leaf::Static => "mcts",
+            Leaf::Rollout(_) => "mcts-rollout",
+            Leaf::Blend(..) => "mcts-blend",
+        }
+    }
+
+    fn search(&mut self, board: &Chessboard, limits: Limits, control: &Control) -> SearchResult {
+        if let Some(result) = self.book.as_ref().and_then(|(book, choice)| book.play(board, *choice)) {
+            return result;
+        }
+        // short forced wins are found far quicker by a proof-number search
+        if let (Outcome::Win(pv), nodes) = pns::solve(board, INSTANT_WIN, control) {
+            return SearchResult {
+                mov: pv[0].clone(),
+                nodes,
+                score: Some(WIN - pv.len() as i64),
+                mate: Some(pv.len() as i64),
+                pv,
+                candidates: vec![],
+            };
+        }
+        if let Some(((r, c, op), value)) = tablebase::installed().and_then(|tablebase| tablebase.best(board)) {
+            let mate = match value {
+                Value::Win(plies) => Some(plies as i64),
+                Value::Loss(plies) => Some(-(plies as i64)),
+                Value::Draw => None,
+            };
+            return SearchResult {
+                mov: (r, c, op.clone()),
+                nodes: 0,
+                score: Some(mate.map_or(0, |plies| plies.signum() * (WIN - plies.abs()))),
+                mate,
+                pv: vec![(r, c, op)],
+                candidates: vec![],
+            };
+        }
+        let root = self.reuse(board).unwrap_or_else(|| Box::new(Node::new(board, self.ply, self.leaf, &self.weights)));
+        let tree = Tree {
+            root: &root,
+            board,
+            leaf: self.leaf,
+            weights: &self.weights,
+            ply: self.ply,
+            nodes: AtomicUsize::new(0),
+            node_limit: limits.nodes.map_or(usize::MAX, |nodes| nodes as usize),
+        };
+        let playouts = run(&tree, &limits, control);
+        let result = result(&root, playouts);
+        self.tree = Some((board.clone(), root));
+        result
+    }
+
+    fn set_weights(&mut self, weights: Weights) {
+        if weights != self.weights {
+            // the kept tree was scored with the old weights
+            self.tree = None;
+        }
+        self.weights = weights;
+    }
+
+    fn set_book(&mut self, book: Option<Opening>) {
+        self.book = book;
+    }
 }
 
-impl ChessEvaluator {
-    fn new(computer: &Turn) -> ChessEvaluator {
-        ChessEvaluator { computer: computer.clone() }
+/// Moves leading from `root` at `board` to an expanded node at `target`.
+fn find(root: &Node, board: &Chessboard, target: &Chessboard, depth: usize) -> Option<Vec<usize>> {
+    if board == target {
+        return Some(vec![]);
+    }
+    if depth == 0 {
+        return None;
     }
+    root.edges.iter().enumerate().find_map(|(i, edge)| {
+        let child = edge.child.get()?;
+        let (r, c, op) = &edge.mov;
+        let mut path = find(child, &board.next(*r, *c, op.clone()).ok()?, target, depth - 1)?;
+        path.insert(0, i);
+        Some(path)
+    })
 }
 
-impl Evaluator<ChessMCTS> for ChessEvaluator {
-    type StateEvaluation = StateEval;
+/// What the playout threads share.
+struct Tree<'a> {
+    root: &'a Node,
+    board: &'a Chessboard,
+    leaf: Leaf,
+    weights: &'a Weights,
+    ply: u32,
+    /// Nodes created by this search.
+    nodes: AtomicUsize,
+    node_limit: usize,
+}
 
-    fn evaluate_new_state(&self, state: &ChessGame, moves: &MoveList<ChessMCTS>, _handle: Option<SearchHandle<ChessMCTS>>) -> (Vec<MoveEvaluation<ChessMCTS>>, Self::StateEvaluation) {
-        match state.board.check() {
-            Status::Win(who) => (vec![(); moves.len()], StateEval::Win(who)),
-            Status::Free(_) => {
-                let (num_a, num_b) = state.board.state();
-                let (num_comp, num_player) = match self.computer {
-                    Turn::A => (num_a, num_b),
-                    Turn::B => (num_b, num_a),
-                };
-                (vec![(); moves.len()], StateEval::Eval((num_player - num_comp) + 5))
+impl Tree<'_> {
+    /// Descends to a new or terminal node and backs its evaluation up the path;
+    /// returns `false` once the node limit is reached.
+    fn playout(&self) -> bool {
+        if self.nodes.load(Ordering::Relaxed) >= self.node_limit {
+            return false;
+        }
+        let mut board = self.board.clone();
+        let mut node = self.root;
+        let mut path: Vec<(&Node, &Edge, &Turn)> = vec![];
+        let evaln = loop {
+            if let Some(evaln) = node.proven() {
+                break evaln;
+            }
+            let player = match &node.player {
+                Some(player) if !node.edges.is_empty() && path.len() < MAX_DEPTH => player,
+                _ => break node.evaln.clone(),
+            };
+            let edge = node.select(player);
+            edge.visits.fetch_add(1, Ordering::Relaxed);
+            path.push((node, edge, player));
+            let (r, c, op) = &edge.mov;
+            board = board.next(*r, *c, op.clone()).unwrap();
+            if let Some(child) = edge.child.get() {
+                node = child;
+                continue;
+            }
+            let ply = self.ply + path.len() as u32;
+            let child = edge.child.get_or_init(|| {
+                self.nodes.fetch_add(1, Ordering::Relaxed);
+                Box::new(Node::new(&board, ply, self.leaf, self.weights))
+            });
+            break child.proven().unwrap_or_else(|| child.evaln.clone());
+        };
+        for (_, edge, player) in &path {
+            edge.sum_rewards.fetch_add(self.interpret(&evaln, player), Ordering::Relaxed);
+        }
+        // proofs only change above a proven node, and stop where a node stays open
+        for (node, _, _) in path.iter().rev() {
+            if !node.prove() {
+                break;
             }
         }
+        true
     }
 
-    fn evaluate_existing_state(&self, _: &ChessGame, existing_evaln: &Self::StateEvaluation, _: SearchHandle<ChessMCTS>) -> Self::StateEvaluation {
-        existing_evaln.clone()
+    fn interpret(&self, evaluation: &StateEval, player: &Turn) -> i64 {
+        match evaluation {
+            StateEval::Win(who, ply) => {
+                let score = WIN - (ply - self.ply) as i64;
+                if who == player { score } else { -score }
+            }
+            StateEval::Draw => 0,
+            StateEval::Eval(x) => match player {
+                Turn::A => *x as i64,
+                Turn::B => -*x as i64,
+            },
+        }
     }
+}
 
-    fn interpret_evaluation_for_player(&self, evaluation: &Self::StateEvaluation, player: &Player<ChessMCTS>) -> i64 {
-        let factor = if *player.as_ref().unwrap() == self.computer { 1 } else { -1 };
-        let eval = match evaluation {
-            StateEval::Win(_) => 1e9 as i64,
-            StateEval::Eval(x) => *x as i64,
-        };
-        factor * eval
+/// How promising each move looks before any search, summing to one: moves that
+/// hand stones to the opponent first, moves that take stones, strand the moved
+/// stone or set up a flip for the opponent last.
+fn priors(board: &Chessboard, moves: &[(i32, i32, Operation)]) -> Vec<f64> {
+    let side = board.turn();
+    let own = match side {
+        Turn::A => Chess::A,
+        Turn::B => Chess::B,
+    };
+    let count = |board: &Chessboard| {
+        let (num_a, num_b) = board.state();
+        if *side == Turn::A { num_a } else { num_b }
+    };
+    let before = count(board);
+    let weights: Vec<f64> = moves
+        .iter()
+        .map(|(r, c, op)| {
+            let next = board.next(*r, *c, op.clone()).unwrap();
+            let mut weight = match count(&next).cmp(&before) {
+                std::cmp::Ordering::Less => SHED,
+                std::cmp::Ordering::Equal => 1.0,
+                std::cmp::Ordering::Greater => TAKE,
+            };
+            if pending(&next, side) {
+                weight *= OPENS;
+            }
+            let (r, c) = match op {
+                Operation::Up => (r - 1, *c),
+                Operation::Down => (r + 1, *c),
+                Operation::Left => (*r, c - 1),
+                Operation::Right => (*r, c + 1),
+                Operation::Pass => return weight,
+            };
+            let neighbours = [(r - 1, c), (r + 1, c), (r, c - 1), (r, c + 1)];
+            let lonely = neighbours
+                .iter()
+                .filter(|&&(r, c)| board.on_board(r, c))
+                .all(|&(r, c)| next.at(r as usize, c as usize) != own);
+            if next.at(r as usize, c as usize) == own && lonely {
+                weight *= ISOLATED;
+            }
+            weight
+        })
+        .collect();
+    let total: f64 = weights.iter().sum();
+    weights.into_iter().map(|weight| weight / total).collect()
+}
+
+/// Whether a snake of `board` with the tense of `side` will flip on the next
+/// move: flips stop after one round, so snakes a flip forms wait for the next
+/// move, unless their cross also holds a snake of the other tense.
+fn pending(board: &Chessboard, side: &Turn) -> bool {
+    let (own, other) = match side {
+        Turn::A => (Tense::A, Tense::B),
+        Turn::B => (Tense::B, Tense::A),
+    };
+    let snakes = count_snake(board);
+    count_cross(&snakes).iter().any(|cross| {
+        let tense = |tense: &Tense| cross.snakes().iter().any(|snake| snake.tense() == tense);
+        tense(&own) && !tense(&other)
+    })
+}
+
+/// Value of a position still in play for A, from -1 to 1.
+fn value(board: &Chessboard, leaf: Leaf, weights: &Weights) -> f64 {
+    match leaf {
+        Leaf::Static => features(board, weights),
+        Leaf::Rollout(policy) => rollout(board, policy, weights),
+        Leaf::Blend(policy, weight) => weight * rollout(board, policy, weights) + (1.0 - weight) * features(board, weights),
     }
 }
 
-#[derive(Default)]
-struct ChessMCTS;
+/// The weighted features for A as a share of the stones on the board, which
+/// the default weights make the share of the stones B has beyond A's; the
+/// result once the game is over.
+fn features(board: &Chessboard, weights: &Weights) -> f64 {
+    match board.check() {
+        Status::Win(Turn::A) => 1.0,
+        Status::Win(Turn::B) => -1.0,
+        Status::Draw => 0.0,
+        Status::Free(_) => {
+            let (num_a, num_b) = board.state();
+            // the score is in hundredths of a stone
+            (weights.score(board, &Turn::A) as f64 / 100.0 / (num_a + num_b) as f64).clamp(-1.0, 1.0)
+        }
+    }
+}
 
-impl MCTS for ChessMCTS {
-    type State = ChessGame;
-    type Eval = ChessEvaluator;
-    type TreePolicy = UCTPolicy;
-    type NodeData = ();
-    type TranspositionTable = ApproxTable<Self>;
-    type ExtraThreadData = ();
+/// Plays the game out with `policy` and scores where it ends.
+fn rollout(board: &Chessboard, policy: Policy, weights: &Weights) -> f64 {
+    let mut rng = rand::rng();
+    let mut board = board.clone();
+    for _ in 0..ROLLOUT_PLIES {
+        if !matches!(board.check(), Status::Free(_)) {
+            break;
+        }
+        let mut moves = board.available();
+        let (r, c, op) = match policy {
+            Policy::Random => match moves.choose(&mut rng) {
+                Some(mov) => mov.clone(),
+                None => break,
+            },
+            Policy::Greedy => {
+                moves.shuffle(&mut rng);
+                let side = board.turn().clone();
+                match moves.into_iter().max_by_key(|(r, c, op)| evaluate(&board.next(*r, *c, op.clone()).unwrap(), &side, weights)) {
+                    Some(mov) => mov,
+                    None => break,
+                }
+            }
+        };
+        board = board.next(r, c, op).unwrap();
+    }
+    features(&board, weights)
+}
 
-    fn cycle_behaviour(&self) -> CycleBehaviour<Self> {
-        CycleBehaviour::UseCurrentEvalWhenCycleDetected
+/// The quickest proven win, else the most visited move not proven lost, with every
+/// root move as a candidate.
+fn result(root: &Node, playouts: u64) -> SearchResult {
+    let player = root.player.as_ref().unwrap();
+    let mut edges: Vec<_> = root.edges.iter().collect();
+    edges.sort_by_key(|edge| {
+        std::cmp::Reverse(match edge.proof().map(|proof| rank(proof, player)) {
+            Some(rank) if rank != 0 => (rank.signum(), rank),
+            _ => (0, edge.visits() as i64),
+        })
+    });
+    let candidates: Vec<_> = edges
+        .into_iter()
+        .map(|edge| Candidate {
+            mov: edge.mov.clone(),
+            visits: edge.visits(),
+            mean: edge.mean(),
+            prior: edge.prior,
+            pv: edge.pv(),
+            mate: edge.proof().and_then(|proof| mate(proof, player)),
+        })
+        .collect();
+    let best = &candidates[0];
+    SearchResult {
+        mov: best.mov.clone(),
+        nodes: playouts,
+        score: (best.visits > 0).then_some(best.mean),
+        pv: best.pv.clone(),
+        mate: best.mate,
+        candidates,
     }
 }
 
-pub fn search(board: &Chessboard, computer: &Turn) -> (i32, i32, Operation) {
-    let game = ChessGame::from(board.clone());
-    let mut mcts = MCTSManager::new(game, ChessMCTS, ChessEvaluator::new(computer), UCTPolicy::new(2_f64.sqrt()), ApproxTable::new(1048576));
-    mcts.playout_n_parallel(1e6 as u32, 16);
-    mcts.best_move().unwrap()
-}
\ No newline at end of file
+/// Runs playouts on every thread until a limit is reached or the search is stopped,
+/// and returns how many were played.
+fn run(tree: &Tree, limits: &Limits, control: &Control) -> u64 {
+    let max_playouts = match limits {
+        Limits { playouts: Some(playouts), .. } => *playouts as u64,
+        Limits { nodes: None, time: None, .. } => PLAYOUTS as u64,
+        _ => u64::MAX,
+    };
+    let deadline = limits.time.map(|time| Instant::now() + time);
+    let threads = limits.threads.unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+    let stop = AtomicBool::new(false);
+    let playouts = AtomicU64::new(0);
+    let reporter = std::thread::current();
+    std::thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| {
+                while !stop.load(Ordering::Relaxed) {
+                    if control.stopped()
+                        || tree.root.proof.get().is_some()
+                        || playouts.fetch_add(1, Ordering::Relaxed) >= max_playouts
+                        || deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                        || !tree.playout() {
+                        stop.store(true, Ordering::Relaxed);
+                        reporter.unpark();
+                    }
+                }
+            });
+        }
+        while !stop.load(Ordering::Relaxed) {
+            std::thread::park_timeout(REPORT_EVERY);
+            control.report(result(tree.root, playouts.load(Ordering::Relaxed).min(max_playouts)));
+        }
+    });
+    playouts.load(Ordering::Relaxed).min(max_playouts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::Rules;
+    use crate::engine::fixtures::{double_threat, mate_in_one, threat};
+
+    /// Searches with the tree alone, without the shortcuts `Mcts::search` takes
+    /// first, so that the scores come from `Tree::interpret`.
+    fn search(board: &Chessboard) -> SearchResult {
+        let weights = Weights::default();
+        let root = Node::new(board, 0, Leaf::Static, &weights);
+        let tree = Tree {
+            root: &root,
+            board,
+            leaf: Leaf::Static,
+            weights: &weights,
+            ply: 0,
+            nodes: AtomicUsize::new(0),
+            node_limit: usize::MAX,
+        };
+        let limits = Limits { playouts: Some(5000), threads: Some(1), ..Limits::default() };
+        result(&root, run(&tree, &limits, &Control::default()))
+    }
+
+    #[test]
+    fn plays_a_win_in_one() {
+        let result = search(&mate_in_one());
+        assert_eq!(result.mov, (1, 1, Operation::Up));
+        assert_eq!(result.mate, Some(1));
+        // every playout through the move ends in A's win one ply on
+        assert_eq!(result.score, Some(WIN - 1));
+    }
+
+    #[test]
+    fn avoids_a_move_that_loses() {
+        let board = threat();
+        let result = search(&board);
+        assert_eq!(result.mate, None);
+        let (r, c, op) = result.mov;
+        let reply = board.next(r, c, op).unwrap();
+        assert!(reply.available().into_iter().all(|(r, c, op)| !matches!(reply.next(r, c, op).unwrap().check(), Status::Win(Turn::A))));
+        // the far stone's moves are proven lost, and score as losses for B
+        let lost: Vec<_> = result.candidates.iter().filter(|candidate| candidate.mov.0 == 5).collect();
+        assert!(!lost.is_empty());
+        for candidate in lost {
+            assert_eq!(candidate.mate, Some(-2));
+            assert!(candidate.visits > 0 && candidate.mean < 0);
+        }
+    }
+
+    #[test]
+    fn plays_down_a_move_that_sets_up_a_flip_for_the_opponent() {
+        let board = Chessboard::from_rows(Rules::default(), &[".BA.", "ABAB", "AA.B"]);
+        let moves = board.available();
+        let priors = priors(&board, &moves);
+        let prior = |mov: (i32, i32, Operation)| priors[moves.iter().position(|m| *m == mov).unwrap()];
+        // moving up flips the top row to A and leaves A, B, A down the second
+        // column, which turns B's stone to A on B's move
+        assert!(pending(&board.next(1, 0, Operation::Up).unwrap(), &Turn::A));
+        assert_eq!(prior((1, 0, Operation::Up)), OPENS * prior((1, 2, Operation::Down)));
+    }
+
+    #[test]
+    fn proves_a_lost_position() {
+        assert_eq!(search(&double_threat(Rules::default())).mate, Some(-2));
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+use crate::chess::handicap::Handicap;
+use crate::chess::{Operation, Rules, TenseRule, PASS};
+
+#[derive(Clone, Debug)]
+pub struct Record {
+    rules: Rules,
+    handicap: Option<Handicap>,
+    seed: Option<u64>,
+    moves: Vec<(i32, i32, Operation)>,
+}
+
+impl Record {
+    pub fn new(rules: Rules, handicap: Option<Handicap>, seed: Option<u64>) -> Record {
+        Record { rules, handicap, seed, moves: vec![] }
+    }
+    pub fn push(&mut self, mov: (i32, i32, Operation)) {
+        self.moves.push(mov);
+    }
+}
+
+/// `[Tag "value"]` lines, a blank line, then one move per line in notation.
+impl Display for Record {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "[Size \"{}\"]", self.rules.size)?;
+        writeln!(f, "[Pass \"{}\"]", switch(self.rules.pass))?;
+        writeln!(f, "[PassFlip \"{}\"]", switch(self.rules.flip_after_pass))?;
+        writeln!(f, "[DoublePassEnds \"{}\"]", switch(self.rules.double_pass_ends))?;
+        writeln!(f, "[MinSnake \"{}\"]", self.rules.min_snake)?;
+        writeln!(f, "[BoundedSnakes \"{}\"]", switch(self.rules.bounded_snakes))?;
+        writeln!(f, "[Tense \"{}\"]", match self.rules.tense {
+            TenseRule::Ends => "ends",
+            TenseRule::EndsAndMiddle => "ends-middle",
+        })?;
+        if let Some(handicap) = &self.handicap {
+            writeln!(f, "[Handicap \"{}\"]", handicap)?;
+        }
+        if let Some(seed) = self.seed {
+            writeln!(f, "[Seed \"{}\"]", seed)?;
+        }
+        writeln!(f)?;
+        for mov in &self.moves {
+            writeln!(f, "{}", notation(mov))?;
+        }
+        Ok(())
+    }
+}
+
+/// `r c op` with 1-based coordinates, e.g. `3 1 D`, or `pass`.
+pub fn notation(mov: &(i32, i32, Operation)) -> String {
+    let op = match mov.2 {
+        Operation::Up => "U",
+        Operation::Down => "D",
+        Operation::Left => "L",
+        Operation::Right => "R",
+        Operation::Pass => return String::from("pass"),
+    };
+    format!("{} {} {}", mov.0 + 1, mov.1 + 1, op)
+}
+
+/// Reads a move written by `notation`.
+pub fn from_notation(text: &str) -> Option<(i32, i32, Operation)> {
+    let parts: Vec<_> = text.split_whitespace().collect();
+    match parts[..] {
+        ["pass"] => Some(PASS),
+        [r, c, op] => {
+            let op = match op {
+                "U" => Operation::Up,
+                "D" => Operation::Down,
+                "L" => Operation::Left,
+                "R" => Operation::Right,
+                _ => return None,
+            };
+            Some((r.parse::<i32>().ok()? - 1, c.parse::<i32>().ok()? - 1, op))
+        }
+        _ => None,
+    }
+}
+
+fn switch(on: bool) -> &'static str {
+    if on { "on" } else { "off" }
+}
@@ -0,0 +1,161 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use rand::Rng;
+
+use crate::arena::MAX_PLIES;
+use crate::chess::layout::fair_seed;
+use crate::chess::{move_index, Chess, Chessboard, Operation, Rules, Status, Turn, MOVES, N};
+use crate::engine::{Control, Engine, Limits};
+
+const MAGIC: &[u8; 4] = b"MYSP";
+const VERSION: u8 = 2;
+/// Opening plies whose moves are drawn in proportion to their visits, so that
+/// games differ beyond their layouts.
+const EXPLORE_PLIES: usize = 8;
+
+/// Self-play games of one engine, written as numbered shard files of training
+/// positions.
+///
+/// A shard starts with a header: `MYSP`, the version byte, `N`, `MOVES` as a
+/// little-endian u16, the rule bytes of `Rules::to_bytes` and the record count
+/// as a little-endian u32. Each record then takes `N * N + 2 + 4 * MOVES` bytes:
+/// the cells row by row (0 empty, 1 A, 2 B), the side to move (0 A, 1 B), the
+/// final result for that side as a signed byte (1 won, 0 drawn, -1 lost), and
+/// the root visits of every move by `move_index` as little-endian u32s. Engines
+/// without visit counts give their chosen move a single visit.
+///
+/// A shard only appears once all its games are written, so an interrupted run
+/// resumes with the first missing shard.
+pub struct Generator {
+    engine: Box<dyn Engine>,
+    rules: Rules,
+    limits: Limits,
+    prefix: PathBuf,
+    games_per_shard: u32,
+}
+
+/// A position of a game still being played, waiting for the result.
+struct Sample {
+    board: Chessboard,
+    visits: Vec<u32>,
+}
+
+impl Generator {
+    pub fn new(engine: Box<dyn Engine>, rules: Rules, limits: Limits, prefix: PathBuf, games_per_shard: u32) -> Generator {
+        Generator { engine, rules, limits, prefix, games_per_shard }
+    }
+
+    /// The first shard not written yet.
+    pub fn next_shard(&self) -> u32 {
+        (0..).find(|&shard| !self.path(shard).exists()).unwrap()
+    }
+
+    pub fn path(&self, shard: u32) -> PathBuf {
+        let mut name = self.prefix.clone().into_os_string();
+        name.push(format!("-{:05}.bin", shard));
+        PathBuf::from(name)
+    }
+
+    /// Plays the games of `shard` and writes them out; `progress` hears of each
+    /// game's length. Returns the positions written.
+    pub fn write_shard(&mut self, shard: u32, mut progress: impl FnMut(usize)) -> io::Result<usize> {
+        let mut records = vec![];
+        for _ in 0..self.games_per_shard {
+            let game = self.play();
+            progress(game.len());
+            records.extend(game);
+        }
+        let path = self.path(shard);
+        let partial = path.with_extension("tmp");
+        write(&partial, &self.rules, &records)?;
+        std::fs::rename(partial, path)?;
+        Ok(records.len())
+    }
+
+    /// One game from a random fair layout, each position with its visits and the
+    /// result for the side that was to move.
+    fn play(&mut self) -> Vec<(Sample, i8)> {
+        let mut board = Chessboard::from_seed(self.rules, fair_seed(self.rules));
+        let mut samples = vec![];
+        let winner = loop {
+            match board.check() {
+                Status::Win(who) => break Some(who),
+                Status::Draw => break None,
+                Status::Free(_) if samples.len() >= MAX_PLIES => break None,
+                Status::Free(_) => {
+                    let result = self.engine.search(&board, self.limits, &Control::default());
+                    let mut visits = vec![0_u32; MOVES];
+                    for candidate in &result.candidates {
+                        visits[move_index(&candidate.mov)] = candidate.visits.min(u32::MAX as u64) as u32;
+                    }
+                    if result.candidates.is_empty() {
+                        visits[move_index(&result.mov)] = 1;
+                    }
+                    let (r, c, op) = if samples.len() < EXPLORE_PLIES {
+                        explore(&board, &visits).unwrap_or(result.mov)
+                    } else {
+                        result.mov
+                    };
+                    samples.push(Sample { board: board.clone(), visits });
+                    board = board.next(r, c, op).unwrap();
+                }
+            }
+        };
+        samples
+            .into_iter()
+            .map(|sample| {
+                let result = match &winner {
+                    Some(who) if who == sample.board.turn() => 1,
+                    Some(_) => -1,
+                    None => 0,
+                };
+                (sample, result)
+            })
+            .collect()
+    }
+}
+
+/// A legal move drawn in proportion to its visits.
+fn explore(board: &Chessboard, visits: &[u32]) -> Option<(i32, i32, Operation)> {
+    let total: u64 = visits.iter().map(|&visits| visits as u64).sum();
+    if total == 0 {
+        return None;
+    }
+    let mut pick = rand::rng().random_range(0..total);
+    for mov in board.available() {
+        let weight = visits[move_index(&mov)] as u64;
+        if pick < weight {
+            return Some(mov);
+        }
+        pick -= weight;
+    }
+    None
+}
+
+fn write(path: &Path, rules: &Rules, records: &[(Sample, i8)]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(MAGIC)?;
+    out.write_all(&[VERSION, N as u8])?;
+    out.write_all(&(MOVES as u16).to_le_bytes())?;
+    out.write_all(&rules.to_bytes())?;
+    out.write_all(&(records.len() as u32).to_le_bytes())?;
+    for (sample, result) in records {
+        for r in 0..N {
+            for c in 0..N {
+                out.write_all(&[match sample.board.at(r, c) {
+                    Chess::Void => 0,
+                    Chess::A => 1,
+                    Chess::B => 2,
+                }])?;
+            }
+        }
+        out.write_all(&[if *sample.board.turn() == Turn::A { 0 } else { 1 }, *result as u8])?;
+        for visits in &sample.visits {
+            out.write_all(&visits.to_le_bytes())?;
+        }
+    }
+    out.flush()
+}